@@ -0,0 +1,55 @@
+//! Low power status LED: double blinks LD3 while sleeping between updates
+#![deny(unsafe_code)]
+#![deny(warnings)]
+#![feature(proc_macro)]
+#![no_std]
+
+extern crate cortex_m_rtfm as rtfm;
+extern crate f3;
+
+use f3::led::{self, Heartbeat, Pattern, LEDS};
+use f3::time::U32Ext;
+use f3::timer::Timer;
+use rtfm::{app, Threshold};
+
+// CONFIGURATION
+const STEP: u32 = 125; // ms
+
+// TASKS & RESOURCES
+app! {
+    device: f3::stm32f30x,
+
+    resources: {
+        static HEARTBEAT: Heartbeat =
+            Heartbeat::new(&LEDS[0], Pattern::DoubleBlink);
+    },
+
+    tasks: {
+        TIM7: {
+            path: heartbeat,
+            resources: [HEARTBEAT, TIM7],
+        },
+    },
+}
+
+// INITIALIZATION PHASE
+fn init(p: init::Peripherals, _r: init::Resources) {
+    led::init(p.GPIOE, p.RCC);
+
+    let timer = Timer(p.TIM7);
+    timer.init(STEP.ms(), p.RCC).unwrap();
+    timer.resume();
+}
+
+// IDLE LOOP
+fn idle() -> ! {
+    // Sleep; the timer interrupt wakes up the processor
+    loop {
+        rtfm::wfi();
+    }
+}
+
+// TASKS
+fn heartbeat(_t: &mut Threshold, r: TIM7::Resources) {
+    r.HEARTBEAT.on_timeout(&Timer(&**r.TIM7));
+}
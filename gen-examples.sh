@@ -14,6 +14,7 @@ main() {
         concurrency
         resource
         preemption
+        heartbeat
     )
 
     rm -rf src/examples
@@ -0,0 +1,454 @@
+//! Analog to Digital Converter
+//!
+//! # ADC1
+//!
+//! - Input = PA1 (channel 2)
+//! - DMA = DMA1 channel 1
+//!
+//! # Continuous sampling
+//!
+//! The ADC can stream conversions into a `CircBuffer` in the background:
+//!
+//! - `init` the `Adc`
+//! - `start` the conversions; this locks the circular buffer
+//! - `read` the half of the circular buffer that the DMA is not filling. Each
+//!   half becomes available once the DMA has completely filled it
+//!
+//! # One-shot sampling
+//!
+//! `read_exact` fills a `Buffer` with consecutive conversions and then stops
+//! requesting DMA transfers. `release` the buffer to get the samples and
+//! `pause` the ADC afterwards.
+//!
+//! # Temperature sensor
+//!
+//! The internal temperature sensor is connected to channel 16. Use
+//! `read_temperature` while the conversions are paused to take a single
+//! reading in degrees Celsius.
+//!
+//! # Reference voltage
+//!
+//! The ADC readings are relative to VDDA. The internal reference voltage
+//! (VREFINT, channel 18) was measured in the factory against a known VDDA,
+//! which lets `read_vdda_mv` compute the actual VDDA and `read_channel_mv`
+//! report readings in millivolts.
+
+use core::marker::Unsize;
+use core::ptr;
+
+use cast::{i16, i32, u16, u32};
+use cortex_m::asm;
+use nb;
+use static_ref::Static;
+use stm32f30x::{ADC1, ADC1_2, DMA1, GPIOA, RCC};
+
+use dma::{self, Buffer, CircBuffer, Dma1Channel1};
+use sampler::Sensor;
+
+/// Temperature sensor reading at 30 C (VDDA = 3.3 V), factory calibrated
+const TS_CAL1: *const u16 = 0x1FFF_F7B8 as *const u16;
+
+/// Temperature sensor reading at 110 C (VDDA = 3.3 V), factory calibrated
+const TS_CAL2: *const u16 = 0x1FFF_F7C2 as *const u16;
+
+/// VREFINT reading at VDDA = 3.3 V, factory calibrated
+const VREFINT_CAL: *const u16 = 0x1FFF_F7BA as *const u16;
+
+/// ADC1
+pub struct Adc<'a>(pub &'a ADC1);
+
+impl<'a> Adc<'a> {
+    /// Initializes the ADC to continuously sample PA1 and to hand over the
+    /// results to the DMA
+    ///
+    /// NOTE After initialization, no conversion will be in progress. Use the
+    /// `start` method to begin sampling
+    pub fn init(
+        &self,
+        adc1_2: &ADC1_2,
+        dma1: &DMA1,
+        gpioa: &GPIOA,
+        rcc: &RCC,
+    ) {
+        let adc1 = self.0;
+
+        // power up peripherals
+        rcc.ahbenr.modify(|_, w| {
+            w.adc12en().set_bit().dmaen().enabled().iopaen().set_bit()
+        });
+
+        // PA1 = analog input
+        gpioa.moder.modify(|_, w| w.moder1().analog());
+
+        // ADC clock = HCLK / 1
+        adc1_2.ccr.modify(|_, w| unsafe { w.ckmode().bits(0b01) });
+
+        // enable the voltage regulator; this takes up to 10 us
+        adc1.cr.modify(|_, w| unsafe { w.advregen().bits(0b00) });
+        adc1.cr.modify(|_, w| unsafe { w.advregen().bits(0b01) });
+        for _ in 0..(::ahb::FREQUENCY / 100_000) {
+            asm::nop();
+        }
+
+        // calibrate (single-ended inputs)
+        adc1.cr.modify(|_, w| w.adcaldif().clear_bit().adcal().set_bit());
+        while adc1.cr.read().adcal().bit_is_set() {}
+
+        // DMA transfer
+        // mem2mem: Memory to memory mode disabled
+        // pl: Medium priority
+        // msize: Memory size = 16 bits
+        // psize: Peripheral size = 16 bits
+        // minc: Memory increment mode enabled
+        // pinc: Peripheral increment mode disabled
+        // circ: Circular mode enabled
+        // dir: Transfer from peripheral to memory
+        // htie: Half transfer interrupt enabled
+        // tceie: Transfer complete interrupt enabled
+        // en: Disabled
+        dma1.ccr1.write(|w| unsafe {
+            w.mem2mem()
+                .clear_bit()
+                .pl()
+                .bits(0b01)
+                .msize()
+                .bits(0b01)
+                .psize()
+                .bits(0b01)
+                .minc()
+                .set_bit()
+                .circ()
+                .set_bit()
+                .pinc()
+                .clear_bit()
+                .dir()
+                .clear_bit()
+                .htie()
+                .set_bit()
+                .tcie()
+                .set_bit()
+                .en()
+                .clear_bit()
+        });
+
+        // sample time = 601.5 ADC clock cycles
+        adc1.smpr1.write(|w| unsafe { w.smp2().bits(0b111) });
+
+        // a single conversion in the regular sequence: channel 2
+        adc1.sqr1.write(|w| unsafe { w.l3().bits(0).sq1().bits(2) });
+
+        // cont: continuous conversion mode
+        // dmacfg: DMA circular mode
+        // dmaen: DMA enabled
+        // res: 12-bit resolution
+        // align: right alignment
+        adc1.cfgr.write(|w| unsafe {
+            w.cont()
+                .set_bit()
+                .dmacfg()
+                .set_bit()
+                .dmaen()
+                .set_bit()
+                .res()
+                .bits(0b00)
+                .align()
+                .clear_bit()
+        });
+
+        adc1.cr.modify(|_, w| w.aden().set_bit());
+        while adc1.isr.read().adrdy().bit_is_clear() {}
+    }
+
+    /// Starts a DMA transfer that continuously streams the conversions into
+    /// `circ_buffer`
+    ///
+    /// This will lock the `circ_buffer`. Use `CircBuffer::read` to access the
+    /// half that the DMA has completely filled
+    pub fn start<B>(
+        &self,
+        circ_buffer: &Static<CircBuffer<B, Dma1Channel1>>,
+        dma1: &DMA1,
+    ) -> Result<(), dma::Error>
+    where
+        B: Unsize<[u16]>,
+    {
+        let adc1 = self.0;
+
+        circ_buffer.start_read_from::<u16, _>(dma1, adc1)?;
+
+        // DMA circular mode
+        adc1.cfgr.modify(|_, w| w.dmacfg().set_bit());
+
+        adc1.cr.modify(|_, w| w.adstart().set_bit());
+
+        Ok(())
+    }
+
+    /// Starts a DMA transfer that fills `buffer` with conversions
+    ///
+    /// The DMA reads the fixed `DR` register and writes into consecutive
+    /// half-words of `buffer` (`Increment::Memory`, the default). This will
+    /// mutably lock the `buffer`; it can be `release`d after the DMA transfer
+    /// finishes. The conversions keep running after that point, `pause` the
+    /// ADC to stop them
+    pub fn read_exact<B>(
+        &self,
+        buffer: &Static<Buffer<B, Dma1Channel1>>,
+        dma1: &DMA1,
+    ) -> Result<(), dma::Error>
+    where
+        B: Unsize<[u16]>,
+    {
+        let adc1 = self.0;
+
+        if dma1.ccr1.read().en().bit_is_set() {
+            return Err(dma::Error::InUse);
+        }
+
+        // DMA one-shot mode
+        dma1.ccr1.modify(|_, w| w.circ().clear_bit());
+        adc1.cfgr.modify(|_, w| w.dmacfg().clear_bit());
+
+        buffer.start_read_from::<u16, _>(dma1, adc1)?;
+
+        adc1.cr.modify(|_, w| w.adstart().set_bit());
+
+        Ok(())
+    }
+
+    /// Reads the internal temperature sensor and returns the temperature in
+    /// degrees Celsius
+    ///
+    /// The temperature is linearly interpolated between the two factory
+    /// calibration points, which were measured with VDDA = 3.3 V. The result
+    /// is only an estimate of the die temperature
+    ///
+    /// This performs a single conversion of channel 16 and then restores the
+    /// configuration set by `init`. The conversions must be paused (see
+    /// `pause`) before calling this method. Returns `None` if the calibration
+    /// values are unusable (e.g. blank) or the result doesn't fit in an `i16`
+    pub fn read_temperature(&self, adc1_2: &ADC1_2) -> Option<i16> {
+        let adc1 = self.0;
+
+        // enable the temperature sensor; it takes up to 10 us to start up
+        adc1_2.ccr.modify(|_, w| w.tsen().set_bit());
+        for _ in 0..(::ahb::FREQUENCY / 100_000) {
+            asm::nop();
+        }
+
+        // sample time = 601.5 ADC clock cycles (the sensor needs > 2.2 us)
+        adc1.smpr2.modify(|_, w| unsafe { w.smp16().bits(0b111) });
+
+        let mut raw = [0];
+        self.read_sequence(&[16], &mut raw);
+        let raw = i32(raw[0]);
+
+        adc1_2.ccr.modify(|_, w| w.tsen().clear_bit());
+
+        // NOTE(unsafe) these addresses are in the read-only system memory
+        let (cal1, cal2) =
+            unsafe { (i32(ptr::read(TS_CAL1)), i32(ptr::read(TS_CAL2))) };
+
+        if cal1 == cal2 {
+            return None;
+        }
+
+        i16((110 - 30) * (raw - cal1) / (cal2 - cal1) + 30).ok()
+    }
+
+    /// Converts each of the `channels`, in order, and stores the results in
+    /// `results`
+    ///
+    /// The channels are converted as a single regular sequence of up to 16
+    /// conversions. The sample time of each channel is left as configured.
+    /// The GPIO pins of external channels must have been put in analog mode
+    /// beforehand
+    ///
+    /// This blocks until the whole sequence has been converted and then
+    /// restores the configuration set by `init`. The conversions must be
+    /// paused (see `pause`) before calling this method
+    ///
+    /// # Panics
+    ///
+    /// If the lengths of `channels` and `results` don't match, if the
+    /// sequence is empty or longer than 16 channels, or if a channel is not
+    /// in the range `1..19`
+    pub fn read_sequence(&self, channels: &[u8], results: &mut [u16]) {
+        let adc1 = self.0;
+
+        assert!(adc1.cr.read().adstart().bit_is_clear());
+        assert_eq!(channels.len(), results.len());
+        assert!(channels.len() >= 1 && channels.len() <= 16);
+
+        let cfgr = adc1.cfgr.read().bits();
+        let sqrs = [
+            adc1.sqr1.read().bits(),
+            adc1.sqr2.read().bits(),
+            adc1.sqr3.read().bits(),
+            adc1.sqr4.read().bits(),
+        ];
+
+        // SQR1 holds the length of the sequence (L = length - 1) followed by
+        // SQ1-SQ4; SQR2 holds SQ5-SQ9; SQR3, SQ10-SQ14; and SQR4, SQ15-SQ16.
+        // Each SQx field is 6 bits wide
+        let mut new = [u32(channels.len() - 1), 0, 0, 0];
+        for (i, &channel) in channels.iter().enumerate() {
+            assert!(channel >= 1 && channel <= 18);
+
+            let (sqr, field) = ((i + 1) / 5, (i + 1) % 5);
+            new[sqr] |= u32(channel) << (6 * field);
+        }
+        adc1.sqr1.write(|w| unsafe { w.bits(new[0]) });
+        adc1.sqr2.write(|w| unsafe { w.bits(new[1]) });
+        adc1.sqr3.write(|w| unsafe { w.bits(new[2]) });
+        adc1.sqr4.write(|w| unsafe { w.bits(new[3]) });
+
+        // single conversion mode without DMA requests
+        adc1.cfgr.modify(|_, w| w.cont().clear_bit().dmaen().clear_bit());
+
+        // NOTE a previous continuous conversion may have left a stale result
+        // (EOC) behind; clear the flags so the first EOC is of this sequence
+        adc1.isr
+            .write(|w| w.eoc().set_bit().eos().set_bit().ovr().set_bit());
+
+        adc1.cr.modify(|_, w| w.adstart().set_bit());
+        for result in results {
+            while adc1.isr.read().eoc().bit_is_clear() {}
+            // NOTE reading DR clears the EOC flag
+            *result = u16(adc1.dr.read().bits()).unwrap();
+        }
+        while adc1.cr.read().adstart().bit_is_set() {}
+
+        // restore the configuration
+        adc1.sqr1.write(|w| unsafe { w.bits(sqrs[0]) });
+        adc1.sqr2.write(|w| unsafe { w.bits(sqrs[1]) });
+        adc1.sqr3.write(|w| unsafe { w.bits(sqrs[2]) });
+        adc1.sqr4.write(|w| unsafe { w.bits(sqrs[3]) });
+        adc1.cfgr.write(|w| unsafe { w.bits(cfgr) });
+    }
+
+    /// Converts `channel` `samples` times and returns the mean of the
+    /// readings
+    ///
+    /// Averaging N samples reduces the uncorrelated noise by a factor of
+    /// `sqrt(N)` at the cost of N times the conversion time, so this is best
+    /// suited to slow changing signals. The conversions must be paused (see
+    /// `pause`) before calling this method
+    ///
+    /// # Panics
+    ///
+    /// If `samples` is zero
+    pub fn read_averaged(&self, channel: u8, samples: u8) -> u16 {
+        assert_ne!(samples, 0);
+
+        // NOTE the sum can't overflow: 255 * 4095 < 2^32
+        let mut sum = 0;
+        for _ in 0..samples {
+            let mut result = [0];
+            self.read_sequence(&[channel], &mut result);
+            sum += u32(result[0]);
+        }
+
+        u16(sum / u32(samples)).unwrap()
+    }
+
+    /// Measures VDDA and returns it in millivolts
+    ///
+    /// The conversions must be paused (see `pause`) before calling this
+    /// method. Returns `None` if the VREFINT reading is unusable (e.g. zero
+    /// because the ADC is not calibrated)
+    pub fn read_vdda_mv(&self, adc1_2: &ADC1_2) -> Option<u16> {
+        let mut raw = [0];
+        self.read_vrefint(None, &mut raw, adc1_2);
+
+        vdda_mv(raw[0])
+    }
+
+    /// Converts `channel` and returns the reading in millivolts
+    ///
+    /// VDDA is measured in the same sequence of conversions so the reading is
+    /// independent of the supply voltage. The conversions must be paused (see
+    /// `pause`) before calling this method. Returns `None` under the same
+    /// conditions as `read_vdda_mv`
+    pub fn read_channel_mv(
+        &self,
+        channel: u8,
+        adc1_2: &ADC1_2,
+    ) -> Option<u16> {
+        let mut raw = [0; 2];
+        self.read_vrefint(Some(channel), &mut raw, adc1_2);
+
+        let vdda = u32(vdda_mv(raw[0])?);
+        Some(u16(u32(raw[1]) * vdda / 4095).unwrap())
+    }
+
+    /// Converts VREFINT, followed by `channel` if any
+    fn read_vrefint(
+        &self,
+        channel: Option<u8>,
+        results: &mut [u16],
+        adc1_2: &ADC1_2,
+    ) {
+        let adc1 = self.0;
+
+        // enable VREFINT; it takes up to 10 us to start up
+        adc1_2.ccr.modify(|_, w| w.vrefen().set_bit());
+        for _ in 0..(::ahb::FREQUENCY / 100_000) {
+            asm::nop();
+        }
+
+        // sample time = 601.5 ADC clock cycles (VREFINT needs > 2.2 us)
+        adc1.smpr2.modify(|_, w| unsafe { w.smp18().bits(0b111) });
+
+        match channel {
+            Some(channel) => self.read_sequence(&[18, channel], results),
+            None => self.read_sequence(&[18], results),
+        }
+
+        adc1_2.ccr.modify(|_, w| w.vrefen().clear_bit());
+    }
+
+    /// Pauses the conversions
+    pub fn pause(&self) {
+        let adc1 = self.0;
+
+        adc1.cr.modify(|_, w| w.adstp().set_bit());
+        while adc1.cr.read().adstp().bit_is_set() {}
+    }
+
+    /// Resumes the conversions
+    pub fn resume(&self) {
+        self.0.cr.modify(|_, w| w.adstart().set_bit());
+    }
+}
+
+/// Converts the PA1 input (channel 2)
+///
+/// The conversions must be paused (see `pause`) while the ADC is used as a
+/// `Sensor`
+impl<'a> Sensor for Adc<'a> {
+    type Output = u16;
+    type Error = !;
+
+    fn measure(&self) -> nb::Result<u16, !> {
+        let mut result = [0];
+        self.read_sequence(&[2], &mut result);
+
+        Ok(result[0])
+    }
+}
+
+/// Computes VDDA, in millivolts, from a VREFINT reading
+///
+/// Returns `None` if the reading is zero or so low that VDDA doesn't fit in
+/// a `u16`
+fn vdda_mv(vrefint: u16) -> Option<u16> {
+    if vrefint == 0 {
+        return None;
+    }
+
+    // NOTE(unsafe) this address is in the read-only system memory
+    let cal = u32(unsafe { ptr::read(VREFINT_CAL) });
+
+    u16(3300 * cal / u32(vrefint)).ok()
+}
@@ -0,0 +1,68 @@
+//! User button
+//!
+//! The user button (B1) is connected to PA0. The board has an external
+//! pull-down resistor on that pin so the pin reads high only while the button
+//! is pressed.
+//!
+//! # Interrupts
+//!
+//! - `EXTI0` - the button was pressed (rising edge on PA0)
+//!
+//! `listen` connects the EXTI0 line to PA0 (see the `exti` module). The
+//! interrupt handler must call `clear_pending` or the interrupt will fire
+//! again as soon as the handler returns.
+
+use stm32f30x::{EXTI, GPIOA, RCC, SYSCFG};
+
+use exti::{self, Edge, Port};
+
+/// The user button
+pub static BUTTON: Button = Button { _0: () };
+
+/// A push button
+pub struct Button {
+    _0: (),
+}
+
+impl Button {
+    /// Returns `true` if the button is currently pressed
+    pub fn is_pressed(&self) -> bool {
+        // NOTE(safe) atomic read
+        unsafe { (*GPIOA.get()).idr.read().idr0().bit_is_set() }
+    }
+
+    /// Returns `true` if the button pin reads high (i.e. it's pressed)
+    pub fn is_high(&self) -> bool {
+        self.is_pressed()
+    }
+
+    /// Returns `true` if the button pin reads low (i.e. it's released)
+    pub fn is_low(&self) -> bool {
+        !self.is_pressed()
+    }
+}
+
+/// Initializes the user button
+pub fn init(gpioa: &GPIOA, rcc: &RCC) {
+    // Power up peripherals
+    rcc.ahbenr.modify(|_, w| w.iopaen().set_bit());
+
+    // Configure PA0 as a floating input; the board provides the pull-down
+    gpioa.moder.modify(|_, w| w.moder0().input());
+    gpioa.pupdr.modify(|_, w| unsafe { w.pupdr0().bits(0b00) });
+}
+
+/// Starts listening for button presses
+pub fn listen(exti: &EXTI, syscfg: &SYSCFG, rcc: &RCC) {
+    exti::configure(0, Port::A, Edge::Rising, exti, syscfg, rcc);
+}
+
+/// Stops listening for button presses
+pub fn unlisten(exti: &EXTI) {
+    exti::mask(0, exti);
+}
+
+/// Clears the pending EXTI0 interrupt
+pub fn clear_pending(exti: &EXTI) {
+    exti::clear_pending(0, exti);
+}
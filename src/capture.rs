@@ -0,0 +1,310 @@
+//! Input capture
+//!
+//! You can use the `Capture` interface with these TIM instances
+//!
+//! # TIM2
+//!
+//! - CH1 = PA0 (NOTE this pin is also connected to the user button)
+//! - CH2 = PA1
+//! - CH3 = PA2
+//! - CH4 = PA3
+//!
+//! # Measuring pulse widths
+//!
+//! Initialize the interface with `Edge::Both`; the difference between two
+//! consecutive captures is then the width of a pulse (or of the gap between
+//! two pulses) in units of the capture resolution. You can tell those two
+//! cases apart by sampling the state of the pin after a capture.
+
+use core::fmt;
+
+use cast::{u16, u32};
+use hal;
+use nb;
+use stm32f30x::{GPIOA, RCC, TIM2};
+
+// Overcapture flags of the `SR` register. The register is `rc_w0`: writing a
+// mask with only the flag to clear set to 0 leaves the other flags untouched,
+// unlike a read-modify-write which would clear the flags that get set between
+// the read and the write
+const CC1OF: u32 = 1 << 9;
+const CC2OF: u32 = 1 << 10;
+const CC3OF: u32 = 1 << 11;
+const CC4OF: u32 = 1 << 12;
+
+/// Input capture error
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Previous capture value was overwritten
+    Overcapture,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Error::Overcapture => "input capture overcapture",
+        })
+    }
+}
+
+/// Input capture channel
+#[derive(Clone, Copy, Debug)]
+pub enum Channel {
+    /// TIM2_CH1
+    _1,
+    /// TIM2_CH2
+    _2,
+    /// TIM2_CH3
+    _3,
+    /// TIM2_CH4
+    _4,
+}
+
+/// Signal edge that triggers a capture
+#[derive(Clone, Copy, Debug)]
+pub enum Edge {
+    /// Rising edge
+    Rising,
+    /// Falling edge
+    Falling,
+    /// Both edges
+    Both,
+}
+
+/// Input capture interface
+pub struct Capture<'a, T>(pub &'a T)
+where
+    T: 'a;
+
+impl<'a> Capture<'a, TIM2> {
+    /// Initializes the input capture interface
+    ///
+    /// `resolution` is the resolution of the capture timer and `edge` selects
+    /// which edge of the input signal triggers a capture
+    ///
+    /// NOTE After initialization, all the channels will be disabled.
+    ///
+    /// # Panics
+    ///
+    /// If `resolution` is zero or longer than `2^16` ticks; the prescaler is
+    /// 16 bits wide. The same applies to `Capture::set_resolution`
+    pub fn init<R>(&self, resolution: R, edge: Edge, gpioa: &GPIOA, rcc: &RCC)
+    where
+        R: Into<::apb1::Ticks>,
+    {
+        self._init(resolution.into(), edge, gpioa, rcc)
+    }
+
+    fn _init(
+        &self,
+        resolution: ::apb1::Ticks,
+        edge: Edge,
+        gpioa: &GPIOA,
+        rcc: &RCC,
+    ) {
+        let tim2 = self.0;
+
+        // power up peripherals
+        rcc.apb1enr.modify(|_, w| w.tim2en().enabled());
+        rcc.ahbenr.modify(|_, w| w.iopaen().set_bit());
+
+        // PA0 = CH1, PA1 = CH2, PA2 = CH3, PA3 = CH4
+        gpioa.afrl.modify(|_, w| unsafe {
+            w.afrl0()
+                .bits(1)
+                .afrl1()
+                .bits(1)
+                .afrl2()
+                .bits(1)
+                .afrl3()
+                .bits(1)
+        });
+        gpioa.moder.modify(|_, w| {
+            w.moder0()
+                .alternate()
+                .moder1()
+                .alternate()
+                .moder2()
+                .alternate()
+                .moder3()
+                .alternate()
+        });
+
+        // CCxS: capture on TIx
+        // ICxF: no filter
+        // ICxPSC: capture on every edge
+        tim2.ccmr1_input.write(|w| unsafe {
+            w.cc1s()
+                .bits(0b01)
+                .ic1f()
+                .bits(0b0000)
+                .ic1psc()
+                .bits(0b00)
+                .cc2s()
+                .bits(0b01)
+                .ic2f()
+                .bits(0b0000)
+                .ic2psc()
+                .bits(0b00)
+        });
+        tim2.ccmr2_input.write(|w| unsafe {
+            w.cc3s()
+                .bits(0b01)
+                .ic3f()
+                .bits(0b0000)
+                .ic3psc()
+                .bits(0b00)
+                .cc4s()
+                .bits(0b01)
+                .ic4f()
+                .bits(0b0000)
+                .ic4psc()
+                .bits(0b00)
+        });
+
+        // CCxNP:CCxP = 00: rising edge, 01: falling edge, 11: both edges
+        let (np, p) = match edge {
+            Edge::Rising => (false, false),
+            Edge::Falling => (false, true),
+            Edge::Both => (true, true),
+        };
+
+        // all channels disabled
+        tim2.ccer.write(|w| {
+            w.cc1np()
+                .bit(np)
+                .cc1p()
+                .bit(p)
+                .cc1e()
+                .clear_bit()
+                .cc2np()
+                .bit(np)
+                .cc2p()
+                .bit(p)
+                .cc2e()
+                .clear_bit()
+                .cc3np()
+                .bit(np)
+                .cc3p()
+                .bit(p)
+                .cc3e()
+                .clear_bit()
+                .cc4np()
+                .bit(np)
+                .cc4p()
+                .bit(p)
+                .cc4e()
+                .clear_bit()
+        });
+
+        // free running counter
+        tim2.arr.write(|w| unsafe { w.bits(u32::max_value()) });
+
+        self._set_resolution(resolution);
+
+        // up counting
+        tim2.cr1.write(|w| unsafe {
+            w.cms()
+                .bits(0b00)
+                .dir()
+                .clear_bit()
+                .opm()
+                .clear_bit()
+                .cen()
+                .set_bit()
+        });
+    }
+
+    fn _set_resolution(&self, resolution: ::apb1::Ticks) {
+        let tim2 = self.0;
+
+        let ticks = resolution.0;
+        assert!(ticks >= 1 && ticks <= 1 << 16, "impossible resolution");
+        let psc = u16(ticks - 1).unwrap();
+        tim2.psc.write(|w| w.psc().bits(psc));
+
+        // load the new prescaler value
+        tim2.egr.write(|w| w.ug().set_bit());
+    }
+}
+
+impl<'a> hal::Capture for Capture<'a, TIM2> {
+    type Capture = u32;
+    type Channel = Channel;
+    type Error = Error;
+    type Time = ::apb1::Ticks;
+
+    fn capture(&self, channel: Channel) -> nb::Result<u32, Error> {
+        let tim2 = self.0;
+        let sr = tim2.sr.read();
+
+        match channel {
+            Channel::_1 => if sr.cc1of().bit_is_set() {
+                tim2.sr.write(|w| unsafe { w.bits(!CC1OF) });
+                Err(nb::Error::Other(Error::Overcapture))
+            } else if sr.cc1if().bit_is_set() {
+                Ok(tim2.ccr1.read().bits())
+            } else {
+                Err(nb::Error::WouldBlock)
+            },
+            Channel::_2 => if sr.cc2of().bit_is_set() {
+                tim2.sr.write(|w| unsafe { w.bits(!CC2OF) });
+                Err(nb::Error::Other(Error::Overcapture))
+            } else if sr.cc2if().bit_is_set() {
+                Ok(tim2.ccr2.read().bits())
+            } else {
+                Err(nb::Error::WouldBlock)
+            },
+            Channel::_3 => if sr.cc3of().bit_is_set() {
+                tim2.sr.write(|w| unsafe { w.bits(!CC3OF) });
+                Err(nb::Error::Other(Error::Overcapture))
+            } else if sr.cc3if().bit_is_set() {
+                Ok(tim2.ccr3.read().bits())
+            } else {
+                Err(nb::Error::WouldBlock)
+            },
+            Channel::_4 => if sr.cc4of().bit_is_set() {
+                tim2.sr.write(|w| unsafe { w.bits(!CC4OF) });
+                Err(nb::Error::Other(Error::Overcapture))
+            } else if sr.cc4if().bit_is_set() {
+                Ok(tim2.ccr4.read().bits())
+            } else {
+                Err(nb::Error::WouldBlock)
+            },
+        }
+    }
+
+    fn disable(&self, channel: Channel) {
+        let ccer = &self.0.ccer;
+
+        match channel {
+            Channel::_1 => ccer.modify(|_, w| w.cc1e().clear_bit()),
+            Channel::_2 => ccer.modify(|_, w| w.cc2e().clear_bit()),
+            Channel::_3 => ccer.modify(|_, w| w.cc3e().clear_bit()),
+            Channel::_4 => ccer.modify(|_, w| w.cc4e().clear_bit()),
+        }
+    }
+
+    fn enable(&self, channel: Channel) {
+        let ccer = &self.0.ccer;
+
+        match channel {
+            Channel::_1 => ccer.modify(|_, w| w.cc1e().set_bit()),
+            Channel::_2 => ccer.modify(|_, w| w.cc2e().set_bit()),
+            Channel::_3 => ccer.modify(|_, w| w.cc3e().set_bit()),
+            Channel::_4 => ccer.modify(|_, w| w.cc4e().set_bit()),
+        }
+    }
+
+    fn get_resolution(&self) -> ::apb1::Ticks {
+        ::apb1::Ticks(u32(self.0.psc.read().psc().bits()) + 1)
+    }
+
+    fn set_resolution<R>(&self, resolution: R)
+    where
+        R: Into<::apb1::Ticks>,
+    {
+        self._set_resolution(resolution.into())
+    }
+}
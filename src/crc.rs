@@ -0,0 +1,51 @@
+//! Software Cyclic Redundancy Checks (CRC)
+//!
+//! These are bitwise, MSB first CRCs with an initial value of zero and no
+//! final XOR. For example, `crc8(data, 0x07)` is CRC-8/SMBUS and
+//! `crc16(data, 0x1021)` is CRC-16/XMODEM.
+
+use cast::u16;
+
+/// Computes the 8-bit CRC of `data` using the polynomial `poly`
+///
+/// `poly` is given in normal representation, i.e. without its implicit
+/// `x^8` term
+pub fn crc8(data: &[u8], poly: u8) -> u8 {
+    let mut crc = 0;
+
+    for byte in data {
+        crc ^= *byte;
+
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// Computes the 16-bit CRC of `data` using the polynomial `poly`
+///
+/// `poly` is given in normal representation, i.e. without its implicit
+/// `x^16` term
+pub fn crc16(data: &[u8], poly: u16) -> u16 {
+    let mut crc = 0;
+
+    for byte in data {
+        crc ^= u16(*byte) << 8;
+
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
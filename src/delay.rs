@@ -0,0 +1,73 @@
+//! Blocking delays
+//!
+//! These delays are backed by the basic timer TIM6 so they can be used when
+//! the SysTick is claimed by the scheduler
+
+use core::{cmp, u16};
+
+use cast::{u16, u32};
+use stm32f30x::{RCC, TIM6};
+
+/// Blocking delay provider
+pub struct Delay<'a>(pub &'a TIM6);
+
+impl<'a> Delay<'a> {
+    /// Initializes the timer to count microseconds
+    pub fn init(&self, rcc: &RCC) {
+        let tim6 = self.0;
+
+        // Power up peripherals
+        rcc.apb1enr.modify(|_, w| w.tim6en().enabled());
+
+        // one tick per microsecond
+        let psc = u16(::apb1::FREQUENCY / 1_000_000 - 1).unwrap();
+        tim6.psc.write(|w| w.psc().bits(psc));
+
+        // stop the count on update events; only overflows set the update flag
+        tim6.cr1.write(|w| w.opm().set_bit().urs().set_bit());
+
+        // load the new prescaler value
+        tim6.egr.write(|w| w.ug().set_bit());
+    }
+
+    /// Blocks for (at least) `ms` milliseconds
+    pub fn delay_ms(&self, ms: u32) {
+        // longest wait that fits in the 16-bit counter
+        const MAX: u32 = u16::MAX as u32 / 1_000;
+
+        let mut ms = ms;
+        while ms != 0 {
+            let n = cmp::min(ms, MAX);
+            self.wait(u16(n * 1_000).unwrap());
+            ms -= n;
+        }
+    }
+
+    /// Blocks for (at least) `us` microseconds
+    pub fn delay_us(&self, us: u32) {
+        let mut us = us;
+        while us != 0 {
+            let n = cmp::min(us, u32(u16::MAX));
+            self.wait(u16(n).unwrap());
+            us -= n;
+        }
+    }
+
+    /// Waits for `us` (non zero) microseconds
+    ///
+    /// NOTE a 1 us wait takes 2 us
+    fn wait(&self, us: u16) {
+        let tim6 = self.0;
+
+        // the timer overflows after ARR + 1 ticks
+        // NOTE(max) the counter doesn't run while ARR is 0 so the update
+        // event would never occur
+        let arr = cmp::max(us, 2) - 1;
+        tim6.arr.write(|w| w.arr().bits(arr));
+        tim6.cnt.write(|w| w.cnt().bits(0));
+        tim6.cr1.modify(|_, w| w.cen().enabled());
+
+        while tim6.sr.read().uif().is_no_update() {}
+        tim6.sr.modify(|_, w| w.uif().clear());
+    }
+}
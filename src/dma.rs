@@ -1,11 +1,15 @@
 //! Direct Memory Access (DMA)
 
 use core::cell::{Cell, UnsafeCell};
-use core::marker::PhantomData;
-use core::ops;
+use core::fmt;
+use core::marker::{PhantomData, Unsize};
+use core::{mem, ops, slice};
 
+use cast::u16;
+use cortex_m::peripheral::NVIC;
 use nb;
-use stm32f30x::DMA1;
+use stm32f30x::{ADC1, DMA1, I2C1, I2C2, Interrupt, RCC, SPI1, SPI2, USART1,
+                USART2, USART3};
 
 /// DMA error
 #[derive(Debug)]
@@ -16,7 +20,182 @@ pub enum Error {
     /// not accessed in a timely fashion
     Overrun,
     /// Transfer error
+    ///
+    /// The DMA hit a bus error while accessing the memory (`CMAR`) or the
+    /// peripheral (`CPAR`) address; this usually means that one of them is
+    /// invalid, e.g. it points into a reserved region or it is a write into
+    /// Flash. The hardware disables the channel when this happens. (DMA1 has
+    /// no FIFO so there are no FIFO errors to report)
     Transfer,
+    /// The buffer has more items than a single transfer can move (65535)
+    TooLarge,
+    /// `Increment::Neither` was used for a transfer of several items
+    InvalidIncrement,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Error::InUse => "DMA channel in use",
+            Error::Overrun => "DMA overrun",
+            Error::Transfer => "DMA transfer error",
+            Error::TooLarge => "DMA transfer too large",
+            Error::InvalidIncrement => "DMA transfer with fixed addresses",
+        })
+    }
+}
+
+/// Priority level of a DMA channel
+///
+/// When several channels have pending requests, the one with the highest
+/// priority level is served first
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Priority {
+    // NOTE the default priority level is zero to keep new buffers in the .bss
+    // section
+    /// Medium priority (default)
+    Medium = 0,
+    /// Low priority
+    Low,
+    /// High priority
+    High,
+    /// Very high priority
+    VeryHigh,
+}
+
+impl Priority {
+    /// Value of the `PL` field of the `CCR` register
+    pub(crate) fn pl(self) -> u8 {
+        match self {
+            Priority::Low => 0b00,
+            Priority::Medium => 0b01,
+            Priority::High => 0b10,
+            Priority::VeryHigh => 0b11,
+        }
+    }
+
+    /// Decodes the `PL` field of the `CCR` register
+    fn from_pl(pl: u8) -> Self {
+        match pl {
+            0b00 => Priority::Low,
+            0b01 => Priority::Medium,
+            0b10 => Priority::High,
+            _ => Priority::VeryHigh,
+        }
+    }
+}
+
+/// Address increment mode of a DMA transfer
+///
+/// - `Memory`: the usual peripheral to memory / memory to peripheral
+///   transfer; the peripheral register is fixed and the DMA walks the buffer
+/// - `Peripheral`: the DMA walks a block of peripheral registers (or memory
+///   mapped as a peripheral) while the memory address is fixed. Useful to
+///   scatter a single value
+/// - `Both`: block copies
+/// - `Neither`: both addresses are fixed; this only makes sense for transfers
+///   of a single item and starting a longer transfer in this mode returns
+///   `Error::InvalidIncrement`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Increment {
+    // NOTE the default mode is zero to keep new buffers in the .bss section
+    /// Increment the memory address only (default)
+    Memory = 0,
+    /// Increment the peripheral address only
+    Peripheral,
+    /// Increment both addresses
+    Both,
+    /// Increment none of the addresses
+    Neither,
+}
+
+impl Increment {
+    /// Value of the `MINC` bit of the `CCR` register
+    pub(crate) fn minc(self) -> bool {
+        self == Increment::Memory || self == Increment::Both
+    }
+
+    /// Value of the `PINC` bit of the `CCR` register
+    pub(crate) fn pinc(self) -> bool {
+        self == Increment::Peripheral || self == Increment::Both
+    }
+
+    /// Decodes the `MINC` and `PINC` bits of the `CCR` register
+    fn from_bits(minc: bool, pinc: bool) -> Self {
+        match (minc, pinc) {
+            (true, false) => Increment::Memory,
+            (false, true) => Increment::Peripheral,
+            (true, true) => Increment::Both,
+            (false, false) => Increment::Neither,
+        }
+    }
+
+    /// Checks that this mode is valid for a transfer of `n` items
+    pub(crate) fn check(self, n: usize) -> Result<(), Error> {
+        if self == Increment::Neither && n > 1 {
+            Err(Error::InvalidIncrement)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A word that the DMA can transfer
+pub unsafe trait Word {
+    /// IMPLEMENTATION DETAIL
+    fn size() -> u8;
+}
+
+unsafe impl Word for u8 {
+    fn size() -> u8 {
+        0b00
+    }
+}
+
+unsafe impl Word for u16 {
+    fn size() -> u8 {
+        0b01
+    }
+}
+
+unsafe impl Word for u32 {
+    fn size() -> u8 {
+        0b10
+    }
+}
+
+/// Memory side of a DMA transfer
+pub(crate) struct Memory {
+    /// Value of the `CMAR` register
+    pub address: u32,
+    /// Value of the `CNDTR` register
+    pub ndt: u16,
+    /// Value of the `MSIZE` field of the `CCR` register
+    pub size: u8,
+}
+
+impl Memory {
+    /// Describes a transfer into / from `words`
+    ///
+    /// The number of items and the item size are derived from `words` so they
+    /// can't get out of sync with the buffer
+    ///
+    /// Returns `Error::TooLarge` if `words` doesn't fit in a single transfer
+    pub fn new<W>(words: &[W]) -> Result<Self, Error>
+    where
+        W: Word,
+    {
+        Ok(Memory {
+            address: words.as_ptr() as u32,
+            ndt: u16(words.len()).map_err(|_| Error::TooLarge)?,
+            size: W::size(),
+        })
+    }
+}
+
+/// Channel 1 of DMA1
+pub struct Dma1Channel1 {
+    _0: (),
 }
 
 /// Channel 2 of DMA1
@@ -24,6 +203,11 @@ pub struct Dma1Channel2 {
     _0: (),
 }
 
+/// Channel 3 of DMA1
+pub struct Dma1Channel3 {
+    _0: (),
+}
+
 /// Channel 4 of DMA1
 pub struct Dma1Channel4 {
     _0: (),
@@ -34,12 +218,173 @@ pub struct Dma1Channel5 {
     _0: (),
 }
 
+/// Channel 6 of DMA1
+pub struct Dma1Channel6 {
+    _0: (),
+}
+
+/// Channel 7 of DMA1
+pub struct Dma1Channel7 {
+    _0: (),
+}
+
+/// A DMA channel that serves the receive (peripheral to memory) requests of
+/// `PERIPHERAL`
+///
+/// Generic transfer functions can use this trait as a bound to reject, at
+/// compile time, a channel that's not wired to the peripheral; see
+/// `Buffer::start_read_from`
+///
+/// # Unsafety
+///
+/// The DMA requests of `PERIPHERAL` must be routed to the implementing
+/// channel and `address` must return the address of the register the
+/// received data is read from
+pub unsafe trait DmaRxChannel<PERIPHERAL> {
+    /// Address of the data register of `peripheral`
+    fn address(peripheral: &PERIPHERAL) -> u32;
+}
+
+/// A DMA channel that serves the transmit (memory to peripheral) requests of
+/// `PERIPHERAL`
+///
+/// See `Buffer::start_write_to`
+///
+/// # Unsafety
+///
+/// The DMA requests of `PERIPHERAL` must be routed to the implementing
+/// channel and `address` must return the address of the register the data
+/// to transmit is written into
+pub unsafe trait DmaTxChannel<PERIPHERAL> {
+    /// Address of the data register of `peripheral`
+    fn address(peripheral: &PERIPHERAL) -> u32;
+}
+
+macro_rules! request {
+    ($Trait:ident, $Dma1ChannelN:ident, $PERIPHERAL:ident, $dr:ident) => {
+        unsafe impl $Trait<$PERIPHERAL> for $Dma1ChannelN {
+            fn address(peripheral: &$PERIPHERAL) -> u32 {
+                &peripheral.$dr as *const _ as u32
+            }
+        }
+    }
+}
+
+request!(DmaRxChannel, Dma1Channel1, ADC1, dr);
+
+request!(DmaRxChannel, Dma1Channel2, SPI1, dr);
+request!(DmaTxChannel, Dma1Channel3, SPI1, dr);
+
+request!(DmaRxChannel, Dma1Channel4, SPI2, dr);
+request!(DmaTxChannel, Dma1Channel5, SPI2, dr);
+
+request!(DmaRxChannel, Dma1Channel7, I2C1, rxdr);
+request!(DmaTxChannel, Dma1Channel6, I2C1, txdr);
+
+request!(DmaRxChannel, Dma1Channel5, I2C2, rxdr);
+request!(DmaTxChannel, Dma1Channel4, I2C2, txdr);
+
+request!(DmaRxChannel, Dma1Channel5, USART1, rdr);
+request!(DmaTxChannel, Dma1Channel4, USART1, tdr);
+
+request!(DmaRxChannel, Dma1Channel6, USART2, rdr);
+request!(DmaTxChannel, Dma1Channel7, USART2, tdr);
+
+request!(DmaRxChannel, Dma1Channel3, USART3, rdr);
+request!(DmaTxChannel, Dma1Channel2, USART3, tdr);
+
+/// The channels of DMA1
+pub struct Dma1 {
+    /// Channel 1
+    pub c1: Dma1Channel1,
+    /// Channel 2
+    pub c2: Dma1Channel2,
+    /// Channel 3
+    pub c3: Dma1Channel3,
+    /// Channel 4
+    pub c4: Dma1Channel4,
+    /// Channel 5
+    pub c5: Dma1Channel5,
+    /// Channel 6
+    pub c6: Dma1Channel6,
+    /// Channel 7
+    pub c7: Dma1Channel7,
+}
+
+/// Powers up DMA1 and splits it into independent channels
+///
+/// NOTE `Buffer`s only use the channel types as type parameters; the channel
+/// values are handles that identify a channel, e.g. to hand it over to a task
+pub fn split(rcc: &RCC) -> Dma1 {
+    rcc.ahbenr.modify(|_, w| w.dmaen().enabled());
+
+    Dma1 {
+        c1: Dma1Channel1 { _0: () },
+        c2: Dma1Channel2 { _0: () },
+        c3: Dma1Channel3 { _0: () },
+        c4: Dma1Channel4 { _0: () },
+        c5: Dma1Channel5 { _0: () },
+        c6: Dma1Channel6 { _0: () },
+        c7: Dma1Channel7 { _0: () },
+    }
+}
+
+/// DMA interrupt event
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// Half of the transfer has been completed
+    HalfTransfer,
+    /// The transfer has been completed
+    TransferComplete,
+    /// A transfer error occurred
+    TransferError,
+}
+
+/// Direction of a DMA transfer
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    /// From the peripheral (`CPAR`) to the memory (`CMAR`)
+    PeripheralToMemory,
+    /// From the memory (`CMAR`) to the peripheral (`CPAR`)
+    MemoryToPeripheral,
+    /// Memory to memory transfer (`MEM2MEM`); the transfer runs without
+    /// waiting for peripheral requests
+    MemoryToMemory,
+}
+
+/// Snapshot of the configuration of a DMA channel, see `describe`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ChannelConfig {
+    /// The channel is enabled
+    pub enabled: bool,
+    /// Direction of the transfer
+    pub direction: Direction,
+    /// Address increment mode
+    pub increment: Increment,
+    /// Size of a memory item in bytes
+    pub memory_size: u8,
+    /// Size of a peripheral item in bytes
+    pub peripheral_size: u8,
+    /// Priority level
+    pub priority: Priority,
+    /// Circular mode
+    pub circular: bool,
+    /// Number of items left to transfer (`CNDTR`)
+    pub ndt: u16,
+    /// Memory address (`CMAR`)
+    pub memory_address: u32,
+    /// Peripheral address (`CPAR`)
+    pub peripheral_address: u32,
+}
+
 /// Buffer to be used with a certain DMA `CHANNEL`
 // NOTE(packed) workaround for rust-lang/rust#41315
 #[repr(packed)]
 pub struct Buffer<T, CHANNEL> {
     data: UnsafeCell<T>,
     flag: Cell<BorrowFlag>,
+    increment: Cell<Increment>,
+    priority: Cell<Priority>,
     state: Cell<State>,
     _marker: PhantomData<CHANNEL>,
 }
@@ -59,16 +404,70 @@ type BorrowFlag = usize;
 const UNUSED: BorrowFlag = 0;
 const WRITING: BorrowFlag = !0;
 
+/// Circular buffer made of two halves, to be used with a certain DMA
+/// `CHANNEL`
+///
+/// The DMA fills the halves alternately; the half that's not being filled can
+/// be accessed through the `read` method
+pub struct CircBuffer<B, CHANNEL> {
+    _marker: PhantomData<CHANNEL>,
+    buffer: UnsafeCell<[B; 2]>,
+    increment: Cell<Increment>,
+    priority: Cell<Priority>,
+    state: Cell<CircState>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CircState {
+    // Not in use by the DMA
+    Free = 0,
+
+    // The DMA is filling the first half
+    MutatingFirstHalf,
+    // The DMA is filling the second half
+    MutatingSecondHalf,
+}
+
 /// Wraps a borrowed reference to a value in a `Buffer`
 pub struct Ref<'a, T>
 where
-    T: 'a,
+    T: 'a + ?Sized,
 {
     data: &'a T,
     flag: &'a Cell<BorrowFlag>,
 }
 
-impl<'a, T> ops::Deref for Ref<'a, T> {
+impl<'a, T> Ref<'a, T>
+where
+    T: ?Sized,
+{
+    /// Makes a new `Ref` for a component of the borrowed data
+    ///
+    /// The `Buffer` remains immutably borrowed until the returned `Ref` exits
+    /// scope
+    ///
+    /// NOTE this is an associated function that needs to be used as
+    /// `Ref::map(...)`; a method would interfere with methods of the same
+    /// name on the contents of the `Buffer`
+    pub fn map<U, F>(orig: Ref<'a, T>, f: F) -> Ref<'a, U>
+    where
+        F: FnOnce(&T) -> &U,
+        U: ?Sized,
+    {
+        let data = f(orig.data);
+        let flag = orig.flag;
+
+        // NOTE the borrow is transferred to the new `Ref`
+        mem::forget(orig);
+
+        Ref { data, flag }
+    }
+}
+
+impl<'a, T> ops::Deref for Ref<'a, T>
+where
+    T: ?Sized,
+{
     type Target = T;
 
     fn deref(&self) -> &T {
@@ -76,7 +475,10 @@ impl<'a, T> ops::Deref for Ref<'a, T> {
     }
 }
 
-impl<'a, T> Drop for Ref<'a, T> {
+impl<'a, T> Drop for Ref<'a, T>
+where
+    T: ?Sized,
+{
     fn drop(&mut self) {
         self.flag.set(self.flag.get() - 1);
     }
@@ -85,13 +487,47 @@ impl<'a, T> Drop for Ref<'a, T> {
 /// A wrapper type for a mutably borrowed value from a `Buffer``
 pub struct RefMut<'a, T>
 where
-    T: 'a,
+    T: 'a + ?Sized,
 {
     data: &'a mut T,
     flag: &'a Cell<BorrowFlag>,
 }
 
-impl<'a, T> ops::Deref for RefMut<'a, T> {
+impl<'a, T> RefMut<'a, T>
+where
+    T: ?Sized,
+{
+    /// Makes a new `RefMut` for a component of the borrowed data
+    ///
+    /// The `Buffer` remains mutably borrowed until the returned `RefMut` exits
+    /// scope
+    ///
+    /// NOTE this is an associated function that needs to be used as
+    /// `RefMut::map(...)`; a method would interfere with methods of the same
+    /// name on the contents of the `Buffer`
+    pub fn map<U, F>(mut orig: RefMut<'a, T>, f: F) -> RefMut<'a, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+        U: ?Sized,
+    {
+        let data: *mut T = orig.data;
+        let flag = orig.flag;
+
+        // NOTE(unsafe) `orig` is forgotten below so this is the only
+        // reference to the data
+        let data = f(unsafe { &mut *data });
+
+        // NOTE the borrow is transferred to the new `RefMut`
+        mem::forget(orig);
+
+        RefMut { data, flag }
+    }
+}
+
+impl<'a, T> ops::Deref for RefMut<'a, T>
+where
+    T: ?Sized,
+{
     type Target = T;
 
     fn deref(&self) -> &T {
@@ -99,13 +535,19 @@ impl<'a, T> ops::Deref for RefMut<'a, T> {
     }
 }
 
-impl<'a, T> ops::DerefMut for RefMut<'a, T> {
+impl<'a, T> ops::DerefMut for RefMut<'a, T>
+where
+    T: ?Sized,
+{
     fn deref_mut(&mut self) -> &mut T {
         self.data
     }
 }
 
-impl<'a, T> Drop for RefMut<'a, T> {
+impl<'a, T> Drop for RefMut<'a, T>
+where
+    T: ?Sized,
+{
     fn drop(&mut self) {
         self.flag.set(UNUSED);
     }
@@ -118,10 +560,47 @@ impl<T, CHANNEL> Buffer<T, CHANNEL> {
             _marker: PhantomData,
             data: UnsafeCell::new(data),
             flag: Cell::new(0),
+            increment: Cell::new(Increment::Memory),
+            priority: Cell::new(Priority::Medium),
             state: Cell::new(State::Unlocked),
         }
     }
 
+    /// Returns the address increment mode of the DMA transfers that use this
+    /// buffer
+    pub fn increment(&self) -> Increment {
+        self.increment.get()
+    }
+
+    /// Changes the address increment mode of the DMA transfers that use this
+    /// buffer
+    ///
+    /// The new mode takes effect the next time a transfer is started
+    pub fn set_increment(&self, increment: Increment) {
+        self.increment.set(increment)
+    }
+
+    /// Returns the priority level of the DMA transfers that use this buffer
+    pub fn priority(&self) -> Priority {
+        self.priority.get()
+    }
+
+    /// Changes the priority level of the DMA transfers that use this buffer
+    ///
+    /// The new priority level takes effect the next time a transfer is
+    /// started
+    pub fn set_priority(&self, priority: Priority) {
+        self.priority.set(priority)
+    }
+
+    /// Returns `true` if a DMA transfer has locked this buffer
+    ///
+    /// The buffer stays locked until it's `release`d, even if the transfer
+    /// has already finished
+    pub fn is_locked(&self) -> bool {
+        self.state.get() != State::Unlocked
+    }
+
     /// Immutably borrows the wrapped value.
     ///
     /// The borrow lasts until the returned `Ref` exits scope. Multiple
@@ -180,6 +659,30 @@ impl<T, CHANNEL> Buffer<T, CHANNEL> {
         unsafe { &mut *self.data.get() }
     }
 
+    /// Releases the buffer without waiting for the transfer to complete
+    ///
+    /// The caller must have disabled the DMA channel beforehand
+    pub(crate) unsafe fn abort(&self) {
+        let state = self.state.get();
+
+        self.unlock(state)
+    }
+
+    /// Resets the buffer to the unlocked and unborrowed state
+    ///
+    /// This is an escape hatch for recovering a buffer after a DMA transfer
+    /// was aborted out of band (e.g. the DMA or the peripheral was reset)
+    /// and the buffer was never `release`d.
+    ///
+    /// # Unsafety
+    ///
+    /// The DMA channel must be disabled and there must be no live `Ref` or
+    /// `RefMut` to the contents of the buffer
+    pub unsafe fn force_unlock(&self) {
+        self.flag.set(UNUSED);
+        self.state.set(State::Unlocked);
+    }
+
     unsafe fn unlock(&self, state: State) {
         match state {
             State::Locked => self.flag.set(self.flag.get() - 1),
@@ -191,69 +694,710 @@ impl<T, CHANNEL> Buffer<T, CHANNEL> {
     }
 }
 
-// FIXME these `release` methods probably want some of sort of barrier
-impl<T> Buffer<T, Dma1Channel2> {
-    /// Waits until the DMA releases this buffer
-    pub fn release(&self, dma1: &DMA1) -> nb::Result<(), Error> {
-        let state = self.state.get();
-
-        if state == State::Unlocked {
-            return Ok(());
+impl<B, CHANNEL> CircBuffer<B, CHANNEL> {
+    /// Creates a new circular buffer from its two halves
+    pub const fn new(buffer: [B; 2]) -> Self {
+        CircBuffer {
+            _marker: PhantomData,
+            buffer: UnsafeCell::new(buffer),
+            increment: Cell::new(Increment::Memory),
+            priority: Cell::new(Priority::Medium),
+            state: Cell::new(CircState::Free),
         }
+    }
 
-        if dma1.isr.read().teif2().bit_is_set() {
-            Err(nb::Error::Other(Error::Transfer))
-        } else if dma1.isr.read().tcif2().bit_is_set() {
-            unsafe { self.unlock(state) }
-            dma1.ifcr.write(|w| w.ctcif2().set_bit());
-            dma1.ccr2.modify(|_, w| w.en().clear_bit());
-            Ok(())
-        } else {
-            Err(nb::Error::WouldBlock)
-        }
+    /// Returns the address increment mode of the DMA transfers that use this
+    /// buffer
+    pub fn increment(&self) -> Increment {
+        self.increment.get()
     }
-}
 
-impl<T> Buffer<T, Dma1Channel4> {
-    /// Waits until the DMA releases this buffer
-    pub fn release(&self, dma1: &DMA1) -> nb::Result<(), Error> {
-        let state = self.state.get();
+    /// Changes the address increment mode of the DMA transfers that use this
+    /// buffer
+    ///
+    /// The new mode takes effect the next time a transfer is started
+    pub fn set_increment(&self, increment: Increment) {
+        self.increment.set(increment)
+    }
 
-        if state == State::Unlocked {
-            return Ok(());
-        }
+    /// Returns the priority level of the DMA transfers that use this buffer
+    pub fn priority(&self) -> Priority {
+        self.priority.get()
+    }
 
-        if dma1.isr.read().teif4().bit_is_set() {
-            Err(nb::Error::Other(Error::Transfer))
-        } else if dma1.isr.read().tcif4().bit_is_set() {
-            unsafe { self.unlock(state) }
-            dma1.ifcr.write(|w| w.ctcif4().set_bit());
-            dma1.ccr4.modify(|_, w| w.en().clear_bit());
-            Ok(())
-        } else {
-            Err(nb::Error::WouldBlock)
-        }
+    /// Changes the priority level of the DMA transfers that use this buffer
+    ///
+    /// The new priority level takes effect the next time a transfer is
+    /// started
+    pub fn set_priority(&self, priority: Priority) {
+        self.priority.set(priority)
+    }
+
+    pub(crate) fn lock(&self) -> &[B; 2] {
+        assert_eq!(self.state.get(), CircState::Free);
+
+        self.state.set(CircState::MutatingFirstHalf);
+
+        unsafe { &*self.buffer.get() }
+    }
+
+    /// Releases the buffer locked by `lock`
+    ///
+    /// The caller must have disabled the DMA channel beforehand
+    pub(crate) unsafe fn abort(&self) {
+        self.state.set(CircState::Free);
     }
 }
 
-impl<T> Buffer<T, Dma1Channel5> {
-    /// Waits until the DMA releases this buffer
-    pub fn release(&self, dma1: &DMA1) -> nb::Result<(), Error> {
-        let state = self.state.get();
+macro_rules! channel {
+    ($Dma1ChannelN:ident,
+     $DMA1_CHN:ident,
+     $ccrN:ident,
+     $cndtrN:ident,
+     $cparN:ident,
+     $cmarN:ident,
+     $htifN:ident,
+     $tcifN:ident,
+     $teifN:ident,
+     $chtifN:ident,
+     $ctcifN:ident,
+     $cteifN:ident) => {
+        impl $Dma1ChannelN {
+            /// Sets the priority of the interrupt of this channel and
+            /// enables it in the NVIC
+            ///
+            /// NOTE only the 4 most significant bits of `priority` are
+            /// implemented; a lower value means a higher priority
+            pub fn enable_interrupt(&self, nvic: &NVIC, priority: u8) {
+                nvic.set_priority(Interrupt::$DMA1_CHN, priority);
+                nvic.enable(Interrupt::$DMA1_CHN);
+            }
+
+            /// Disables the interrupt of this channel in the NVIC
+            pub fn disable_interrupt(&self, nvic: &NVIC) {
+                nvic.disable(Interrupt::$DMA1_CHN);
+            }
+
+            /// Returns the current configuration of this channel
+            ///
+            /// This only reads the channel registers; it's meant for
+            /// debugging transfers that don't behave as expected
+            pub fn describe(&self, dma1: &DMA1) -> ChannelConfig {
+                let ccr = dma1.$ccrN.read();
 
-        if state == State::Unlocked {
-            return Ok(());
+                let direction = if ccr.mem2mem().bit_is_set() {
+                    Direction::MemoryToMemory
+                } else if ccr.dir().bit_is_set() {
+                    Direction::MemoryToPeripheral
+                } else {
+                    Direction::PeripheralToMemory
+                };
+
+                ChannelConfig {
+                    enabled: ccr.en().bit_is_set(),
+                    direction: direction,
+                    increment: Increment::from_bits(
+                        ccr.minc().bit_is_set(),
+                        ccr.pinc().bit_is_set(),
+                    ),
+                    memory_size: 1 << ccr.msize().bits(),
+                    peripheral_size: 1 << ccr.psize().bits(),
+                    priority: Priority::from_pl(ccr.pl().bits()),
+                    circular: ccr.circ().bit_is_set(),
+                    ndt: dma1.$cndtrN.read().ndt().bits(),
+                    memory_address: dma1.$cmarN.read().bits(),
+                    peripheral_address: dma1.$cparN.read().bits(),
+                }
+            }
         }
 
-        if dma1.isr.read().teif5().bit_is_set() {
-            Err(nb::Error::Other(Error::Transfer))
-        } else if dma1.isr.read().tcif5().bit_is_set() {
-            unsafe { self.unlock(state) }
-            dma1.ifcr.write(|w| w.ctcif5().set_bit());
-            dma1.ccr5.modify(|_, w| w.en().clear_bit());
-            Ok(())
-        } else {
-            Err(nb::Error::WouldBlock)
+        impl<B> CircBuffer<B, $Dma1ChannelN> {
+            /// Starts a circular DMA transfer from the peripheral register at
+            /// `address` into this buffer
+            ///
+            /// The DMA fills the two halves of the buffer one after the other,
+            /// forever. Use `read` to access the half that has been filled
+            /// while the DMA fills the other one (ping-pong buffering)
+            ///
+            /// # Unsafety
+            ///
+            /// Same as `Buffer::start_read`
+            pub unsafe fn start_read<W>(
+                &self,
+                dma1: &DMA1,
+                address: u32,
+            ) -> Result<(), Error>
+            where
+                B: Unsize<[W]>,
+                W: Word,
+            {
+                if dma1.$ccrN.read().en().bit_is_set() {
+                    return Err(Error::InUse);
+                }
+
+                // NOTE `B` is an array of `W`s
+                let increment = self.increment();
+                increment
+                    .check(2 * mem::size_of::<B>() / mem::size_of::<W>())?;
+
+                let memory = {
+                    let buffer: &[B; 2] = self.lock();
+                    let half: &[W] = &buffer[0];
+                    // NOTE the two halves are contiguous in memory
+                    let words =
+                        slice::from_raw_parts(half.as_ptr(), 2 * half.len());
+                    Memory::new(words)
+                };
+                let memory = match memory {
+                    Ok(memory) => memory,
+                    Err(e) => {
+                        // the channel is disabled
+                        self.abort();
+                        return Err(e);
+                    }
+                };
+
+                let priority = self.priority();
+
+                // discard the events of a previous transfer
+                dma1.ifcr.write(|w| {
+                    w.$chtifN()
+                        .set_bit()
+                        .$ctcifN()
+                        .set_bit()
+                        .$cteifN()
+                        .set_bit()
+                });
+
+                dma1.$cndtrN.write(|w| w.ndt().bits(memory.ndt));
+                dma1.$cparN.write(|w| w.bits(address));
+                dma1.$cmarN.write(|w| w.bits(memory.address));
+                // NOTE the channel may have been left in memory to memory
+                // mode by a previous user
+                dma1.$ccrN.modify(|_, w| {
+                    w.mem2mem()
+                        .clear_bit()
+                        .minc()
+                        .bit(increment.minc())
+                        .pinc()
+                        .bit(increment.pinc())
+                        .pl()
+                        .bits(priority.pl())
+                        .msize()
+                        .bits(memory.size)
+                        .psize()
+                        .bits(memory.size)
+                        .circ()
+                        .set_bit()
+                        .dir()
+                        .clear_bit()
+                        .en()
+                        .set_bit()
+                });
+
+                Ok(())
+            }
+
+            /// Starts a circular DMA transfer from the data register of
+            /// `peripheral` into this buffer
+            ///
+            /// This is the safe version of `start_read`; it only compiles if
+            /// the requests of `peripheral` are routed to this channel
+            pub fn start_read_from<W, P>(
+                &self,
+                dma1: &DMA1,
+                peripheral: &P,
+            ) -> Result<(), Error>
+            where
+                B: Unsize<[W]>,
+                W: Word,
+                $Dma1ChannelN: DmaRxChannel<P>,
+            {
+                let address =
+                    <$Dma1ChannelN as DmaRxChannel<P>>::address(peripheral);
+
+                // NOTE(unsafe) the requests are routed to this channel
+                unsafe { self.start_read::<W>(dma1, address) }
+            }
+
+            /// Yields read access to the half of the circular buffer that's
+            /// not currently being filled by the DMA
+            ///
+            /// Returns `WouldBlock` until the next half has been completely
+            /// filled. Returns `Error::Overrun` if the DMA wrote into the half
+            /// being read before `f` returned; the half is only lent for the
+            /// duration of `f` so that this can be checked
+            ///
+            /// # Panics
+            ///
+            /// Panics if no DMA transfer is using this buffer
+            pub fn read<R, F>(
+                &self,
+                dma1: &DMA1,
+                f: F,
+            ) -> nb::Result<R, Error>
+            where
+                F: FnOnce(&B) -> R,
+            {
+                let state = self.state.get();
+
+                assert_ne!(state, CircState::Free);
+
+                let isr = dma1.isr.read();
+
+                if isr.$teifN().bit_is_set() {
+                    return Err(nb::Error::Other(Error::Transfer));
+                }
+
+                match state {
+                    CircState::MutatingFirstHalf => {
+                        if isr.$tcifN().bit_is_set() {
+                            Err(nb::Error::Other(Error::Overrun))
+                        } else if isr.$htifN().bit_is_set() {
+                            dma1.ifcr.write(|w| w.$chtifN().set_bit());
+
+                            self.state.set(CircState::MutatingSecondHalf);
+
+                            let ret = f(unsafe { &(*self.buffer.get())[0] });
+
+                            if dma1.isr.read().$tcifN().bit_is_set() {
+                                Err(nb::Error::Other(Error::Overrun))
+                            } else {
+                                Ok(ret)
+                            }
+                        } else {
+                            Err(nb::Error::WouldBlock)
+                        }
+                    }
+                    CircState::MutatingSecondHalf => {
+                        if isr.$htifN().bit_is_set() {
+                            Err(nb::Error::Other(Error::Overrun))
+                        } else if isr.$tcifN().bit_is_set() {
+                            dma1.ifcr.write(|w| w.$ctcifN().set_bit());
+
+                            self.state.set(CircState::MutatingFirstHalf);
+
+                            let ret = f(unsafe { &(*self.buffer.get())[1] });
+
+                            if dma1.isr.read().$htifN().bit_is_set() {
+                                Err(nb::Error::Other(Error::Overrun))
+                            } else {
+                                Ok(ret)
+                            }
+                        } else {
+                            Err(nb::Error::WouldBlock)
+                        }
+                    }
+                    CircState::Free => unreachable!(),
+                }
+            }
+
+            /// Stops the circular transfer and unlocks this buffer
+            ///
+            /// This disables the channel and clears its circular mode. Does
+            /// nothing if no transfer is using this buffer
+            pub fn stop(&self, dma1: &DMA1) {
+                if self.state.get() == CircState::Free {
+                    return;
+                }
+
+                dma1.$ccrN
+                    .modify(|_, w| w.en().clear_bit().circ().clear_bit());
+                dma1.ifcr.write(|w| {
+                    w.$chtifN()
+                        .set_bit()
+                        .$ctcifN()
+                        .set_bit()
+                        .$cteifN()
+                        .set_bit()
+                });
+
+                // NOTE(unsafe) the channel is disabled
+                unsafe { self.abort() }
+            }
+        }
+
+        // FIXME these `release` methods probably want some of sort of barrier
+        impl<T> Buffer<T, $Dma1ChannelN> {
+            /// Starts a DMA transfer from the peripheral register at
+            /// `address` into this buffer
+            ///
+            /// This will mutably lock the buffer preventing borrowing its
+            /// contents. The buffer can be `release`d after the DMA transfer
+            /// finishes
+            ///
+            /// # Unsafety
+            ///
+            /// `address` must be the address of a peripheral data register
+            /// whose DMA requests are routed to this channel. The peripheral
+            /// register is accessed with the width of `W`
+            pub unsafe fn start_read<W>(
+                &self,
+                dma1: &DMA1,
+                address: u32,
+            ) -> Result<(), Error>
+            where
+                T: Unsize<[W]>,
+                W: Word,
+            {
+                if dma1.$ccrN.read().en().bit_is_set() {
+                    return Err(Error::InUse);
+                }
+
+                // NOTE `T` is an array of `W`s
+                self.increment()
+                    .check(mem::size_of::<T>() / mem::size_of::<W>())?;
+
+                let memory = {
+                    let words: &mut [W] = self.lock_mut();
+                    Memory::new(words)
+                };
+
+                self.start(dma1, address, memory, false)
+            }
+
+            /// Starts a DMA transfer from this buffer into the peripheral
+            /// register at `address`
+            ///
+            /// This will immutably lock the buffer preventing mutably
+            /// borrowing its contents. The buffer can be `release`d after the
+            /// DMA transfer finishes
+            ///
+            /// # Unsafety
+            ///
+            /// Same as `start_read`
+            pub unsafe fn start_write<W>(
+                &self,
+                dma1: &DMA1,
+                address: u32,
+            ) -> Result<(), Error>
+            where
+                T: Unsize<[W]>,
+                W: Word,
+            {
+                if dma1.$ccrN.read().en().bit_is_set() {
+                    return Err(Error::InUse);
+                }
+
+                // NOTE `T` is an array of `W`s
+                self.increment()
+                    .check(mem::size_of::<T>() / mem::size_of::<W>())?;
+
+                let memory = {
+                    let words: &[W] = self.lock();
+                    Memory::new(words)
+                };
+
+                self.start(dma1, address, memory, true)
+            }
+
+            /// Starts a DMA transfer from the data register of `peripheral`
+            /// into this buffer
+            ///
+            /// This is the safe version of `start_read`; it only compiles if
+            /// the receive requests of `peripheral` are routed to this channel
+            pub fn start_read_from<W, P>(
+                &self,
+                dma1: &DMA1,
+                peripheral: &P,
+            ) -> Result<(), Error>
+            where
+                T: Unsize<[W]>,
+                W: Word,
+                $Dma1ChannelN: DmaRxChannel<P>,
+            {
+                let address =
+                    <$Dma1ChannelN as DmaRxChannel<P>>::address(peripheral);
+
+                // NOTE(unsafe) the requests are routed to this channel
+                unsafe { self.start_read::<W>(dma1, address) }
+            }
+
+            /// Starts a DMA transfer from this buffer into the data register
+            /// of `peripheral`
+            ///
+            /// This is the safe version of `start_write`; it only compiles if
+            /// the transmit requests of `peripheral` are routed to this
+            /// channel
+            pub fn start_write_to<W, P>(
+                &self,
+                dma1: &DMA1,
+                peripheral: &P,
+            ) -> Result<(), Error>
+            where
+                T: Unsize<[W]>,
+                W: Word,
+                $Dma1ChannelN: DmaTxChannel<P>,
+            {
+                let address =
+                    <$Dma1ChannelN as DmaTxChannel<P>>::address(peripheral);
+
+                // NOTE(unsafe) the requests are routed to this channel
+                unsafe { self.start_write::<W>(dma1, address) }
+            }
+
+            /// Programs and enables the channel; `dir` is set for memory to
+            /// peripheral transfers
+            unsafe fn start(
+                &self,
+                dma1: &DMA1,
+                address: u32,
+                memory: Result<Memory, Error>,
+                dir: bool,
+            ) -> Result<(), Error> {
+                let memory = match memory {
+                    Ok(memory) => memory,
+                    Err(e) => {
+                        // the channel is disabled
+                        self.abort();
+                        return Err(e);
+                    }
+                };
+
+                let increment = self.increment();
+                let priority = self.priority();
+
+                // discard the events of a previous transfer
+                dma1.ifcr.write(|w| {
+                    w.$chtifN()
+                        .set_bit()
+                        .$ctcifN()
+                        .set_bit()
+                        .$cteifN()
+                        .set_bit()
+                });
+
+                dma1.$cndtrN.write(|w| w.ndt().bits(memory.ndt));
+                dma1.$cparN.write(|w| w.bits(address));
+                dma1.$cmarN.write(|w| w.bits(memory.address));
+                // NOTE the channel may have been left in circular or memory to
+                // memory mode by a previous user
+                dma1.$ccrN.modify(|_, w| {
+                    w.mem2mem()
+                        .clear_bit()
+                        .circ()
+                        .clear_bit()
+                        .minc()
+                        .bit(increment.minc())
+                        .pinc()
+                        .bit(increment.pinc())
+                        .pl()
+                        .bits(priority.pl())
+                        .msize()
+                        .bits(memory.size)
+                        .psize()
+                        .bits(memory.size)
+                        .dir()
+                        .bit(dir)
+                        .en()
+                        .set_bit()
+                });
+
+                Ok(())
+            }
+
+            /// Waits until the DMA releases this buffer
+            ///
+            /// If the transfer failed the buffer is released as well and
+            /// `Error::Transfer` is returned
+            pub fn release(&self, dma1: &DMA1) -> nb::Result<(), Error> {
+                let state = self.state.get();
+
+                if state == State::Unlocked {
+                    return Ok(());
+                }
+
+                if dma1.isr.read().$teifN().bit_is_set() {
+                    dma1.ifcr.write(|w| w.$cteifN().set_bit());
+                    dma1.$ccrN.modify(|_, w| w.en().clear_bit());
+                    unsafe { self.unlock(state) }
+                    Err(nb::Error::Other(Error::Transfer))
+                } else if dma1.isr.read().$tcifN().bit_is_set() {
+                    unsafe { self.unlock(state) }
+                    dma1.ifcr.write(|w| w.$ctcifN().set_bit());
+                    dma1.$ccrN.modify(|_, w| w.en().clear_bit());
+                    Ok(())
+                } else {
+                    Err(nb::Error::WouldBlock)
+                }
+            }
+
+            /// Blocks until the DMA releases this buffer
+            ///
+            /// This is the blocking version of `release`
+            pub fn wait(&self, dma1: &DMA1) -> Result<(), Error> {
+                block!(self.release(dma1))
+            }
+
+            /// Starts listening for an interrupt `event` on the DMA channel
+            /// associated to this buffer
+            pub fn listen(&self, dma1: &DMA1, event: Event) {
+                let ccr = &dma1.$ccrN;
+
+                match event {
+                    Event::HalfTransfer => {
+                        ccr.modify(|_, w| w.htie().set_bit())
+                    }
+                    Event::TransferComplete => {
+                        ccr.modify(|_, w| w.tcie().set_bit())
+                    }
+                    Event::TransferError => {
+                        ccr.modify(|_, w| w.teie().set_bit())
+                    }
+                }
+            }
+
+            /// Stops listening for an interrupt `event` on the DMA channel
+            /// associated to this buffer
+            pub fn unlisten(&self, dma1: &DMA1, event: Event) {
+                let ccr = &dma1.$ccrN;
+
+                match event {
+                    Event::HalfTransfer => {
+                        ccr.modify(|_, w| w.htie().clear_bit())
+                    }
+                    Event::TransferComplete => {
+                        ccr.modify(|_, w| w.tcie().clear_bit())
+                    }
+                    Event::TransferError => {
+                        ccr.modify(|_, w| w.teie().clear_bit())
+                    }
+                }
+            }
+
+            /// Services the interrupt of the DMA channel associated to this
+            /// buffer
+            ///
+            /// This clears the flag of the event that fired the interrupt
+            /// and reports the event. On transfer complete, the buffer is
+            /// released. On transfer error, the DMA has already disabled the
+            /// channel so the buffer is released as well and
+            /// `Error::Transfer` is returned
+            ///
+            /// Returns `WouldBlock` if no event is pending
+            pub fn handle_interrupt(
+                &self,
+                dma1: &DMA1,
+            ) -> nb::Result<Event, Error> {
+                let isr = dma1.isr.read();
+
+                if isr.$teifN().bit_is_set() {
+                    dma1.ifcr.write(|w| w.$cteifN().set_bit());
+                    dma1.$ccrN.modify(|_, w| w.en().clear_bit());
+                    unsafe { self.unlock(self.state.get()) }
+                    Err(nb::Error::Other(Error::Transfer))
+                } else if isr.$tcifN().bit_is_set() {
+                    dma1.ifcr
+                        .write(|w| w.$ctcifN().set_bit().$chtifN().set_bit());
+                    dma1.$ccrN.modify(|_, w| w.en().clear_bit());
+                    unsafe { self.unlock(self.state.get()) }
+                    Ok(Event::TransferComplete)
+                } else if isr.$htifN().bit_is_set() {
+                    dma1.ifcr.write(|w| w.$chtifN().set_bit());
+                    Ok(Event::HalfTransfer)
+                } else {
+                    Err(nb::Error::WouldBlock)
+                }
+            }
         }
     }
 }
+
+channel!(
+    Dma1Channel1,
+    DMA1_CH1,
+    ccr1,
+    cndtr1,
+    cpar1,
+    cmar1,
+    htif1,
+    tcif1,
+    teif1,
+    chtif1,
+    ctcif1,
+    cteif1
+);
+channel!(
+    Dma1Channel2,
+    DMA1_CH2,
+    ccr2,
+    cndtr2,
+    cpar2,
+    cmar2,
+    htif2,
+    tcif2,
+    teif2,
+    chtif2,
+    ctcif2,
+    cteif2
+);
+channel!(
+    Dma1Channel3,
+    DMA1_CH3,
+    ccr3,
+    cndtr3,
+    cpar3,
+    cmar3,
+    htif3,
+    tcif3,
+    teif3,
+    chtif3,
+    ctcif3,
+    cteif3
+);
+channel!(
+    Dma1Channel4,
+    DMA1_CH4,
+    ccr4,
+    cndtr4,
+    cpar4,
+    cmar4,
+    htif4,
+    tcif4,
+    teif4,
+    chtif4,
+    ctcif4,
+    cteif4
+);
+channel!(
+    Dma1Channel5,
+    DMA1_CH5,
+    ccr5,
+    cndtr5,
+    cpar5,
+    cmar5,
+    htif5,
+    tcif5,
+    teif5,
+    chtif5,
+    ctcif5,
+    cteif5
+);
+channel!(
+    Dma1Channel6,
+    DMA1_CH6,
+    ccr6,
+    cndtr6,
+    cpar6,
+    cmar6,
+    htif6,
+    tcif6,
+    teif6,
+    chtif6,
+    ctcif6,
+    cteif6
+);
+channel!(
+    Dma1Channel7,
+    DMA1_CH7,
+    ccr7,
+    cndtr7,
+    cpar7,
+    cmar7,
+    htif7,
+    tcif7,
+    teif7,
+    chtif7,
+    ctcif7,
+    cteif7
+);
@@ -1,57 +1,45 @@
 //! Serial interface loopback
 //!
 //! ```
-//! 
-//! #![feature(const_fn)]
-//! #![feature(used)]
+//! #![deny(unsafe_code)]
+//! #![deny(warnings)]
+//! #![feature(proc_macro)]
 //! #![no_std]
 //! 
-//! // version = "0.2.0"
-//! extern crate cortex_m_rt;
-//! 
-//! // version = "0.1.0"
-//! #[macro_use]
 //! extern crate cortex_m_rtfm as rtfm;
-//! 
 //! extern crate f3;
 //! 
-//! use f3::serial::Serial;
-//! use f3::stm32f30x::interrupt::Usart1Exti25;
-//! use f3::stm32f30x;
-//! use rtfm::{P0, P1, T0, T1, TMax};
+//! use f3::prelude::*;
+//! use f3::Serial;
+//! use f3::serial::Event;
+//! use f3::time::Hertz;
+//! use rtfm::{app, Threshold};
 //! 
 //! // CONFIGURATION
-//! pub const BAUD_RATE: u32 = 115_200; // bits per second
+//! const BAUD_RATE: Hertz = Hertz(115_200);
 //! 
-//! // RESOURCES
-//! peripherals!(stm32f30x, {
-//!     GPIOA: Peripheral {
-//!         register_block: Gpioa,
-//!         ceiling: C0,
-//!     },
-//!     RCC: Peripheral {
-//!         register_block: Rcc,
-//!         ceiling: C0,
-//!     },
-//!     USART1: Peripheral {
-//!         register_block: Usart1,
-//!         ceiling: C1,
-//!     },
-//! });
+//! // TASKS & RESOURCES
+//! app! {
+//!     device: f3::stm32f30x,
 //! 
-//! // INITIALIZATION PHASE
-//! fn init(ref priority: P0, threshold: &TMax) {
-//!     let gpioa = GPIOA.access(priority, threshold);
-//!     let rcc = RCC.access(priority, threshold);
-//!     let usart1 = USART1.access(priority, threshold);
+//!     tasks: {
+//!         USART1_EXTI25: {
+//!             path: loopback,
+//!             resources: [USART1],
+//!         },
+//!     }
+//! }
 //! 
-//!     let serial = Serial(&usart1);
+//! // INITIALIZATION PHASE
+//! fn init(p: init::Peripherals) {
+//!     let serial = Serial(p.USART1);
 //! 
-//!     serial.init(&gpioa, &rcc, BAUD_RATE);
+//!     serial.init(BAUD_RATE.invert(), Some(p.DMA1), p.GPIOA, p.RCC);
+//!     serial.listen(Event::Rxne);
 //! }
 //! 
 //! // IDLE LOOP
-//! fn idle(_priority: P0, _threshold: T0) -> ! {
+//! fn idle() -> ! {
 //!     // Sleep
 //!     loop {
 //!         rtfm::wfi();
@@ -59,31 +47,12 @@
 //! }
 //! 
 //! // TASKS
-//! tasks!(stm32f30x, {
-//!     loopback: Task {
-//!         interrupt: Usart1Exti25,
-//!         priority: P1,
-//!         enabled: true,
-//!     },
-//! });
-//! 
 //! // Send back the received byte
-//! fn loopback(_task: Usart1Exti25, ref priority: P1, ref threshold: T1) {
-//!     let usart1 = USART1.access(priority, threshold);
-//!     let serial = Serial(&usart1);
+//! fn loopback(_t: &mut Threshold, r: USART1_EXTI25::Resources) {
+//!     let serial = Serial(&**r.USART1);
 //! 
-//!     if let Ok(byte) = serial.read() {
-//!         if serial.write(byte).is_err() {
-//!             // As we are echoing the bytes as soon as they arrive, it should
-//!             // be impossible to have a TX buffer overrun
-//!             #[cfg(debug_assertions)]
-//!             unreachable!()
-//!         }
-//!     } else {
-//!         // Only reachable through `rtfm::request(loopback)`
-//!         #[cfg(debug_assertions)]
-//!         unreachable!()
-//!     }
+//!     let byte = serial.read().unwrap();
+//!     serial.write(byte).unwrap();
 //! }
 //! ```
 // Auto-generated. Do not modify.
@@ -11,121 +11,73 @@
 //!   spinning in the same direction.
 //!
 //! ```
-//! 
+//! #![deny(unsafe_code)]
+//! #![deny(warnings)]
 //! #![feature(const_fn)]
-//! #![feature(used)]
+//! #![feature(proc_macro)]
 //! #![no_std]
 //! 
-//! // version = "0.2.2", default-features = false
 //! extern crate cast;
-//! 
-//! // version = "0.2.0"
-//! extern crate cortex_m_rt;
-//! 
-//! // version = "0.1.0"
-//! #[macro_use]
+//! extern crate cortex_m;
 //! extern crate cortex_m_rtfm as rtfm;
-//! 
 //! extern crate f3;
-//! 
-//! // version = "0.1.0"
 //! extern crate heapless;
 //! 
-//! use core::cell::Cell;
-//! 
-//! use cast::{u8, usize};
+//! use cast::{usize, u8};
+//! use cortex_m::peripheral::SystClkSource;
+//! use f3::Serial;
 //! use f3::led::{self, LEDS};
-//! use f3::serial::Serial;
-//! use f3::stm32f30x::interrupt::{Tim7, Usart1Exti25};
-//! use f3::stm32f30x;
-//! use f3::timer::Timer;
+//! use f3::prelude::*;
+//! use f3::serial::Event;
+//! use f3::time::Hertz;
 //! use heapless::Vec;
-//! use rtfm::{C1, Local, P0, P1, Resource, T0, T1, TMax};
+//! use rtfm::{app, Threshold};
 //! 
-//! // SUPPORT CODE
-//! struct State {
-//!     direction: Cell<Direction>,
-//!     mode: Cell<Mode>,
-//! }
+//! // CONFIGURATION
+//! const BAUD_RATE: Hertz = Hertz(115_200);
+//! const DIVISOR: u32 = 4; 
+//! 
+//! // TASKS & RESOURCES
+//! app! {
+//!     device: f3::stm32f30x,
+//! 
+//!     resources: {
+//!         // 16 byte buffer
+//!         static BUFFER: Vec<u8, [u8; 16]> = Vec::new([0; 16]);
+//!         static SHARED: State = State::new();
+//!         static STATE: u8 = 0;
+//!     },
 //! 
-//! impl State {
-//!     const fn new() -> Self {
-//!         State {
-//!             direction: Cell::new(Direction::Clockwise),
-//!             mode: Cell::new(Mode::Continuous),
-//!         }
-//!     }
-//! }
+//!     tasks: {
+//!         USART1_EXTI25: {
+//!             path: receive,
+//!             resources: [BUFFER, SHARED, USART1],
+//!         },
 //! 
-//! #[derive(Clone, Copy)]
-//! enum Direction {
-//!     Clockwise,
-//!     Counterclockwise,
-//! }
-//! 
-//! impl Direction {
-//!     fn reverse(self) -> Self {
-//!         match self {
-//!             Direction::Clockwise => Direction::Counterclockwise,
-//!             Direction::Counterclockwise => Direction::Clockwise,
-//!         }
+//!         SYS_TICK: {
+//!             path: roulette,
+//!             resources: [SHARED, STATE],
+//!         },
 //!     }
 //! }
 //! 
-//! #[derive(Clone, Copy, PartialEq)]
-//! enum Mode {
-//!     Bounce,
-//!     Continuous,
-//! }
+//! // INITIALIZATION PHASE
+//! fn init(p: init::Peripherals, _r: init::Resources) {
+//!     led::init(&p.GPIOE, &p.RCC);
 //! 
-//! // CONFIGURATION
-//! pub const BAUD_RATE: u32 = 115_200; // bits per second
-//! const FREQUENCY: u32 = 4; // Hz
-//! 
-//! // RESOURCES
-//! peripherals!(stm32f30x, {
-//!     GPIOA: Peripheral {
-//!         register_block: Gpioa,
-//!         ceiling: C0,
-//!     },
-//!     GPIOE: Peripheral {
-//!         register_block: Gpioe,
-//!         ceiling: C0,
-//!     },
-//!     RCC: Peripheral {
-//!         register_block: Rcc,
-//!         ceiling: C0,
-//!     },
-//!     TIM7: Peripheral {
-//!         register_block: Tim7,
-//!         ceiling: C1,
-//!     },
-//!     USART1: Peripheral {
-//!         register_block: Usart1,
-//!         ceiling: C1,
-//!     },
-//! });
+//!     let serial = Serial(p.USART1);
+//!     serial.init(BAUD_RATE.invert(), Some(p.DMA1), p.GPIOA, p.RCC);
+//!     serial.listen(Event::Rxne);
 //! 
-//! static SHARED: Resource<State, C1> = Resource::new(State::new());
 //! 
-//! // INITIALIZATION PHASE
-//! fn init(ref priority: P0, threshold: &TMax) {
-//!     let gpioa = GPIOA.access(priority, threshold);
-//!     let gpioe = GPIOE.access(priority, threshold);
-//!     let rcc = RCC.access(priority, threshold);
-//!     let tim7 = TIM7.access(priority, threshold);
-//!     let timer = Timer(&tim7);
-//!     let usart1 = USART1.access(priority, threshold);
-//! 
-//!     led::init(&gpioe, &rcc);
-//!     timer.init(&rcc, FREQUENCY);
-//!     Serial(&usart1).init(&gpioa, &rcc, BAUD_RATE);
-//! 
-//!     timer.resume();
+//!     p.SYST.set_clock_source(SystClkSource::Core);
+//!     p.SYST.set_reload(8_000_000 / DIVISOR);
+//!     p.SYST.enable_interrupt();
+//!     p.SYST.enable_counter();
 //! }
 //! 
 //! // IDLE LOOP
-//! fn idle(_priority: P0, _threshold: T0) -> ! {
+//! fn idle() -> ! {
 //!     // Sleep
 //!     loop {
 //!         rtfm::wfi();
@@ -133,102 +85,93 @@
 //! }
 //! 
 //! // TASKS
-//! tasks!(stm32f30x, {
-//!     roulette: Task {
-//!         interrupt: Tim7,
-//!         priority: P1,
-//!         enabled: true,
-//!     },
-//!     receive: Task {
-//!         interrupt: Usart1Exti25,
-//!         priority: P1,
-//!         enabled: true,
-//!     },
-//! });
-//! 
-//! fn receive(mut task: Usart1Exti25, ref priority: P1, ref threshold: T1) {
-//!     // 16 byte buffer
-//!     static BUFFER: Local<Vec<u8, [u8; 16]>, Usart1Exti25> = {
-//!         Local::new(Vec::new([0; 16]))
-//!     };
-//! 
-//!     let usart1 = USART1.access(priority, threshold);
-//!     let serial = Serial(&usart1);
+//! fn receive(_t: &mut Threshold, r: USART1_EXTI25::Resources) {
+//!     let serial = Serial(&**r.USART1);
 //! 
-//!     if let Ok(byte) = serial.read() {
-//!         if serial.write(byte).is_err() {
-//!             // As we are echoing the bytes as soon as they arrive, it should
-//!             // be impossible to have a TX buffer overrun
-//!             #[cfg(debug_assertions)]
-//!             unreachable!()
-//!         }
+//!     let byte = serial.read().unwrap();
 //! 
-//!         let buffer = BUFFER.borrow_mut(&mut task);
+//!     serial.write(byte).unwrap();
 //! 
-//!         if byte == b'r' {
-//!             // end of command
+//!     if byte == b'r' {
+//!         // end of command
 //! 
-//!             let shared = SHARED.access(priority, threshold);
-//!             match &**buffer {
-//!                 b"bounce" => shared.mode.set(Mode::Bounce),
-//!                 b"continuous" => shared.mode.set(Mode::Continuous),
-//!                 b"reverse" => {
-//!                     shared.direction.set(shared.direction.get().reverse());
-//!                 }
-//!                 _ => {}
+//!         match &***r.BUFFER {
+//!             b"bounce" => r.SHARED.mode = Mode::Bounce,
+//!             b"continuous" => r.SHARED.mode = Mode::Continuous,
+//!             b"reverse" => {
+//!                 r.SHARED.direction = r.SHARED.direction.reverse();
 //!             }
+//!             _ => {}
+//!         }
 //! 
-//!             // clear the buffer to prepare for the next command
-//!             buffer.clear();
-//!         } else {
-//!             // push the byte into the buffer
+//!         // clear the buffer to prepare for the next command
+//!         r.BUFFER.clear();
+//!     } else {
+//!         // push the byte into the buffer
 //! 
-//!             if buffer.push(byte).is_err() {
-//!                 // error: buffer full
-//!                 // KISS: we just clear the buffer when it gets full
-//!                 buffer.clear();
-//!             }
+//!         if r.BUFFER.push(byte).is_err() {
+//!             // error: buffer full
+//!             // KISS: we just clear the buffer when it gets full
+//!             r.BUFFER.clear();
 //!         }
-//!     } else {
-//!         // Only reachable through `rtfm::request(receive)`
-//!         #[cfg(debug_assertions)]
-//!         unreachable!()
 //!     }
 //! }
 //! 
-//! fn roulette(mut task: Tim7, ref priority: P1, ref threshold: T1) {
-//!     static STATE: Local<u8, Tim7> = Local::new(0);
+//! fn roulette(_t: &mut Threshold, r: SYS_TICK::Resources) {
+//!     let curr = **r.STATE;
 //! 
-//!     let tim7 = TIM7.access(priority, threshold);
-//!     let timer = Timer(&tim7);
+//!     let mut direction = r.SHARED.direction;
+//!     if curr == 0 && r.SHARED.mode == Mode::Bounce {
+//!         direction = direction.reverse();
+//!         r.SHARED.direction = direction;
+//!     }
 //! 
-//!     if timer.clear_update_flag().is_ok() {
-//!         let state = STATE.borrow_mut(&mut task);
-//!         let curr = *state;
+//!     let n = u8(LEDS.len()).unwrap();
+//!     let next = match direction {
+//!         Direction::Clockwise => (curr + 1) % n,
+//!         Direction::Counterclockwise => curr.checked_sub(1).unwrap_or(n - 1),
+//!     };
 //! 
-//!         let shared = SHARED.access(priority, threshold);
-//!         let mut direction = shared.direction.get();
+//!     LEDS[usize(curr)].off();
+//!     LEDS[usize(next)].on();
 //! 
-//!         if curr == 0 && shared.mode.get() == Mode::Bounce {
-//!             direction = direction.reverse();
-//!             shared.direction.set(direction);
-//!         }
+//!     **r.STATE = next;
+//! }
 //! 
-//!         let n = u8(LEDS.len()).unwrap();
-//!         let next = match direction {
-//!             Direction::Clockwise => (curr + 1) % n,
-//!             Direction::Counterclockwise => curr.checked_sub(1).unwrap_or(n - 1),
-//!         };
+//! // SUPPORT CODE
+//! pub struct State {
+//!     direction: Direction,
+//!     mode: Mode,
+//! }
 //! 
-//!         LEDS[usize(curr)].off();
-//!         LEDS[usize(next)].on();
+//! impl State {
+//!     const fn new() -> Self {
+//!         State {
+//!             direction: Direction::Clockwise,
+//!             mode: Mode::Continuous,
+//!         }
+//!     }
+//! }
 //! 
-//!         *state = next;
-//!     } else {
-//!         // Only reachable through `rtfm::request(roulette)`
-//!         #[cfg(debug_assertion)]
-//!         unreachable!()
+//! #[derive(Clone, Copy)]
+//! enum Direction {
+//!     Clockwise,
+//!     Counterclockwise,
+//! }
+//! 
+//! impl Direction {
+//!     fn reverse(self) -> Self {
+//!         match self {
+//!             Direction::Clockwise => Direction::Counterclockwise,
+//!             Direction::Counterclockwise => Direction::Clockwise,
+//!         }
 //!     }
 //! }
+//! 
+//! #[derive(Clone, Copy, PartialEq)]
+//! enum Mode {
+//!     Bounce,
+//!     Continuous,
+//! }
 //! ```
 // Auto-generated. Do not modify.
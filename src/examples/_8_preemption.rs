@@ -8,122 +8,73 @@
 //! - `reset` - moves the roulette back to its start position (North)
 //!
 //! ```
-//! 
+//! #![deny(unsafe_code)]
+//! #![deny(warnings)]
 //! #![feature(const_fn)]
-//! #![feature(used)]
+//! #![feature(proc_macro)]
 //! #![no_std]
 //! 
-//! // version = "0.2.2", default-features = false
 //! extern crate cast;
-//! 
-//! // version = "0.2.0"
-//! extern crate cortex_m_rt;
-//! 
-//! // version = "0.1.0"
-//! #[macro_use]
+//! extern crate cortex_m;
 //! extern crate cortex_m_rtfm as rtfm;
-//! 
 //! extern crate f3;
-//! 
-//! // version = "0.1.0"
 //! extern crate heapless;
 //! 
-//! use core::cell::Cell;
-//! 
-//! use cast::{u8, usize};
+//! use cast::{usize, u8};
+//! use cortex_m::peripheral::SystClkSource;
+//! use f3::Serial;
 //! use f3::led::{self, LEDS};
-//! use f3::serial::Serial;
-//! use f3::stm32f30x::interrupt::{Tim7, Usart1Exti25};
-//! use f3::stm32f30x;
-//! use f3::timer::Timer;
+//! use f3::prelude::*;
+//! use f3::serial::Event;
 //! use heapless::Vec;
-//! use rtfm::{C2, Local, P0, P1, P2, Resource, T0, T1, T2, TMax};
-//! 
-//! // SUPPORT CODE
-//! #[derive(Clone, Copy)]
-//! enum Direction {
-//!     Clockwise,
-//!     Counterclockwise,
-//! }
+//! use rtfm::{app, Resource, Threshold};
+//! use f3::time::Hertz;
 //! 
-//! impl Direction {
-//!     fn reverse(self) -> Self {
-//!         match self {
-//!             Direction::Clockwise => Direction::Counterclockwise,
-//!             Direction::Counterclockwise => Direction::Clockwise,
-//!         }
-//!     }
-//! }
+//! // CONFIGURATION
+//! const BAUD_RATE: Hertz = Hertz(115_200);
+//! const DIVISOR: u32 = 4;
 //! 
-//! #[derive(Clone, Copy, PartialEq)]
-//! enum Mode {
-//!     Bounce,
-//!     Continuous,
-//! }
+//! // TASK & RESOURCES
+//! app!{
+//!     device: f3::stm32f30x,
 //! 
-//! struct State {
-//!     direction: Cell<Direction>,
-//!     mode: Cell<Mode>,
-//! }
+//!     resources: {
+//!         static BUFFER: Vec<u8, [u8; 16]> = Vec::new([0; 16]);
+//!         static SHARED: State = State::new();
+//!         static STATE: u8 = 0;
+//!     },
 //! 
-//! impl State {
-//!     const fn new() -> Self {
-//!         State {
-//!             direction: Cell::new(Direction::Clockwise),
-//!             mode: Cell::new(Mode::Continuous),
-//!         }
-//!     }
+//!     tasks: {
+//!         USART1_EXTI25: {
+//!             path: receive,
+//!             priority: 1,
+//!             resources: [BUFFER, SHARED, USART1],
+//!         },
+//! 
+//!         SYS_TICK: {
+//!             path: roulette,
+//!             priority: 2,
+//!             resources: [SHARED, STATE],
+//!         },
+//!     },
 //! }
 //! 
-//! // CONFIGURATION
-//! pub const BAUD_RATE: u32 = 115_200; // bits per second
-//! const FREQUENCY: u32 = 4; // Hz
-//! 
-//! // RESOURCES
-//! peripherals!(stm32f30x, {
-//!     GPIOA: Peripheral {
-//!         register_block: Gpioa,
-//!         ceiling: C0,
-//!     },
-//!     GPIOE: Peripheral {
-//!         register_block: Gpioe,
-//!         ceiling: C0,
-//!     },
-//!     RCC: Peripheral {
-//!         register_block: Rcc,
-//!         ceiling: C0,
-//!     },
-//!     TIM7: Peripheral {
-//!         register_block: Tim7,
-//!         ceiling: C2, // was `C1`
-//!     },
-//!     USART1: Peripheral {
-//!         register_block: Usart1,
-//!         ceiling: C1,
-//!     },
-//! });
+//! // INITIALIZATION PHASE
+//! fn init(p: init::Peripherals, _r: init::Resources) {
+//!     led::init(&p.GPIOE, &p.RCC);
 //! 
-//! // the ceiling was `C1`
-//! static SHARED: Resource<State, C2> = Resource::new(State::new());
+//!     let serial = Serial(p.USART1);
+//!     serial.init(BAUD_RATE.invert(), Some(p.DMA1), p.GPIOA, p.RCC);
+//!     serial.listen(Event::Rxne);
 //! 
-//! // INITIALIZATION PHASE
-//! fn init(ref priority: P0, threshold: &TMax) {
-//!     let gpioa = GPIOA.access(priority, threshold);
-//!     let gpioe = GPIOE.access(priority, threshold);
-//!     let rcc = RCC.access(priority, threshold);
-//!     let tim7 = TIM7.access(priority, threshold);
-//!     let timer = Timer(&tim7);
-//!     let usart1 = USART1.access(priority, threshold);
-//! 
-//!     led::init(&gpioe, &rcc);
-//!     timer.init(&rcc, FREQUENCY);
-//!     Serial(&usart1).init(&gpioa, &rcc, BAUD_RATE);
-//! 
-//!     timer.resume();
+//!     p.SYST.set_clock_source(SystClkSource::Core);
+//!     p.SYST.set_reload(8_000_000 / DIVISOR);
+//!     p.SYST.enable_interrupt();
+//!     p.SYST.enable_counter();
 //! }
 //! 
 //! // IDLE LOOP
-//! fn idle(_priority: P0, _threshold: T0) -> ! {
+//! fn idle() -> ! {
 //!     // Sleep
 //!     loop {
 //!         rtfm::wfi();
@@ -131,113 +82,104 @@
 //! }
 //! 
 //! // TASKS
-//! tasks!(stm32f30x, {
-//!     roulette: Task {
-//!         interrupt: Tim7,
-//!         priority: P2, // changed to `P2`
-//!         enabled: true,
-//!     },
-//!     receive: Task {
-//!         interrupt: Usart1Exti25,
-//!         priority: P1,
-//!         enabled: true,
-//!     },
-//! });
+//! fn receive(t: &mut Threshold, mut r: USART1_EXTI25::Resources) {
+//!     let serial = Serial(&**r.USART1);
 //! 
-//! fn receive(mut task: Usart1Exti25, ref priority: P1, ref threshold: T1) {
-//!     static BUFFER: Local<Vec<u8, [u8; 16]>, Usart1Exti25> = {
-//!         Local::new(Vec::new([0; 16]))
-//!     };
+//!     let byte = serial.read().unwrap();
+//!     if serial.write(byte).is_err() {
+//!         // As we are echoing the bytes as soon as they arrive, it should
+//!         // be impossible to have a TX buffer overrun
+//!         #[cfg(debug_assertions)]
+//!         unreachable!()
+//!     }
 //! 
-//!     let usart1 = USART1.access(priority, threshold);
-//!     let serial = Serial(&usart1);
+//!     if byte == b'r' {
+//!         // end of command
 //! 
-//!     if let Ok(byte) = serial.read() {
-//!         if serial.write(byte).is_err() {
-//!             // As we are echoing the bytes as soon as they arrive, it should
-//!             // be impossible to have a TX buffer overrun
-//!             #[cfg(debug_assertions)]
-//!             unreachable!()
-//!         }
-//! 
-//!         let buffer = BUFFER.borrow_mut(&mut task);
-//! 
-//!         if byte == b'r' {
-//!             // end of command
-//! 
-//!             match &**buffer {
-//!                 b"bounce" => {
-//!                     threshold.raise(
-//!                         &SHARED, |threshold| {
-//!                             let shared = SHARED.access(priority, threshold);
-//!                             shared.mode.set(Mode::Bounce)
-//!                         }
-//!                     );
-//!                 }
-//!                 b"continuous" => {
-//!                     threshold.raise(
-//!                         &SHARED, |threshold| {
-//!                             let shared = SHARED.access(priority, threshold);
-//!                             shared.mode.set(Mode::Continuous)
-//!                         }
-//!                     );
-//!                 }
-//!                 b"reverse" => {
-//!                     threshold.raise(&SHARED, |threshold| {
-//!                         let shared = SHARED.access(priority, threshold);
-//!                         shared.direction.set(shared.direction.get().reverse());
-//!                     });
-//!                 }
-//!                 _ => {}
+//!         match &***r.BUFFER {
+//!             b"bounce" => {
+//!                 r.SHARED
+//!                     .claim_mut(t, |shared, _| { shared.mode = Mode::Bounce; });
 //!             }
-//! 
-//!             buffer.clear();
-//!         } else {
-//!             if buffer.push(byte).is_err() {
-//!                 // error: buffer full
-//!                 // KISS: we just clear the buffer when it gets full
-//!                 buffer.clear();
+//!             b"continuous" => {
+//!                 r.SHARED.claim_mut(
+//!                     t,
+//!                     |shared, _| { shared.mode = Mode::Continuous; },
+//!                 );
+//!             }
+//!             b"reverse" => {
+//!                 r.SHARED.claim_mut(t, |shared, _| {
+//!                     shared.direction = shared.direction.reverse();
+//!                 });
 //!             }
+//!             _ => {}
 //!         }
+//! 
+//!         r.BUFFER.clear();
 //!     } else {
-//!         // Only reachable through `rtfm::request(receive)`
-//!         #[cfg(debug_assertions)]
-//!         unreachable!()
+//!         if r.BUFFER.push(byte).is_err() {
+//!             // error: buffer full
+//!             // KISS: we just clear the buffer when it gets full
+//!             r.BUFFER.clear();
+//!         }
 //!     }
 //! }
 //! 
-//! fn roulette(mut task: Tim7, ref priority: P2, ref threshold: T2) {
-//!     static STATE: Local<u8, Tim7> = Local::new(0);
+//! fn roulette(_t: &mut Threshold, r: SYS_TICK::Resources) {
+//!     let curr = **r.STATE;
+//! 
+//!     let mut direction = r.SHARED.direction;
 //! 
-//!     let tim7 = TIM7.access(priority, threshold);
-//!     let timer = Timer(&tim7);
+//!     if curr == 0 && r.SHARED.mode == Mode::Bounce {
+//!         direction = direction.reverse();
+//!         r.SHARED.direction = direction;
+//!     }
+//! 
+//!     let n = u8(LEDS.len()).unwrap();
+//!     let next = match direction {
+//!         Direction::Clockwise => (curr + 1) % n,
+//!         Direction::Counterclockwise => curr.checked_sub(1).unwrap_or(n - 1),
+//!     };
 //! 
-//!     if timer.clear_update_flag().is_ok() {
-//!         let state = STATE.borrow_mut(&mut task);
-//!         let curr = *state;
+//!     LEDS[usize(curr)].off();
+//!     LEDS[usize(next)].on();
 //! 
-//!         let shared = SHARED.access(priority, threshold);
-//!         let mut direction = shared.direction.get();
+//!     **r.STATE = next;
+//! }
+//! 
+//! // SUPPORT CODE
+//! #[derive(Clone, Copy)]
+//! enum Direction {
+//!     Clockwise,
+//!     Counterclockwise,
+//! }
 //! 
-//!         if curr == 0 && shared.mode.get() == Mode::Bounce {
-//!             direction = direction.reverse();
-//!             shared.direction.set(direction);
+//! impl Direction {
+//!     fn reverse(self) -> Self {
+//!         match self {
+//!             Direction::Clockwise => Direction::Counterclockwise,
+//!             Direction::Counterclockwise => Direction::Clockwise,
 //!         }
+//!     }
+//! }
 //! 
-//!         let n = u8(LEDS.len()).unwrap();
-//!         let next = match direction {
-//!             Direction::Clockwise => (curr + 1) % n,
-//!             Direction::Counterclockwise => curr.checked_sub(1).unwrap_or(n - 1),
-//!         };
+//! #[derive(Clone, Copy, PartialEq)]
+//! enum Mode {
+//!     Bounce,
+//!     Continuous,
+//! }
 //! 
-//!         LEDS[usize(curr)].off();
-//!         LEDS[usize(next)].on();
+//! pub struct State {
+//!     direction: Direction,
+//!     mode: Mode,
+//! }
 //! 
-//!         *state = next;
-//!     } else {
-//!         // Only reachable through `rtfm::request(roulette)`
-//!         #[cfg(debug_assertion)]
-//!         unreachable!()
+//! impl State {
+//!     const fn new() -> Self {
+//!         State {
+//!             direction: Direction::Clockwise,
+//!             mode: Mode::Continuous,
+//!         }
 //!     }
 //! }
 //! ```
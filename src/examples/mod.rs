@@ -9,3 +9,4 @@ pub mod _5_loopback;
 pub mod _6_concurrency;
 pub mod _7_resource;
 pub mod _8_preemption;
+pub mod _9_heartbeat;
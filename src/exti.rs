@@ -0,0 +1,122 @@
+//! External interrupt / event controller
+//!
+//! Lines 0 to 15 of the EXTI controller can be driven by the pin with the
+//! same number of any GPIO port. For example, line 0 can be connected to PA0,
+//! PB0, ..., PF0, but only to one of them at a time.
+//!
+//! # Interrupts
+//!
+//! - `EXTI0`, `EXTI1`, `EXTI2_TS`, `EXTI3` and `EXTI4` - lines 0 to 4
+//! - `EXTI9_5` - lines 5 to 9
+//! - `EXTI15_10` - lines 10 to 15
+//!
+//! The interrupt handler must call `clear_pending` or the interrupt will fire
+//! again as soon as the handler returns.
+
+use stm32f30x::{EXTI, RCC, SYSCFG};
+
+/// GPIO port that drives an EXTI line
+#[derive(Clone, Copy, Debug)]
+pub enum Port {
+    /// GPIOA
+    A,
+    /// GPIOB
+    B,
+    /// GPIOC
+    C,
+    /// GPIOD
+    D,
+    /// GPIOE
+    E,
+    /// GPIOF
+    F,
+}
+
+/// Signal edge that triggers an interrupt
+#[derive(Clone, Copy, Debug)]
+pub enum Edge {
+    /// Rising edge
+    Rising,
+    /// Falling edge
+    Falling,
+    /// Both edges
+    Both,
+}
+
+/// Connects the EXTI `line` to the pin `line` of `port` and unmasks its
+/// interrupt
+///
+/// # Panics
+///
+/// If `line` is not in the range `0..16`
+pub fn configure(
+    line: u8,
+    port: Port,
+    edge: Edge,
+    exti: &EXTI,
+    syscfg: &SYSCFG,
+    rcc: &RCC,
+) {
+    assert!(line < 16);
+
+    // Power up peripherals
+    rcc.apb2enr.modify(|_, w| w.syscfgen().enabled());
+
+    // each EXTICR register holds 4 fields of 4 bits; 0b0000 selects port A,
+    // 0b0001 port B, etc.
+    let shift = 4 * (line % 4);
+    let mask = 0b1111 << shift;
+    let bits = (port as u32) << shift;
+    match line / 4 {
+        0 => syscfg.exticr1.modify(|r, w| unsafe {
+            w.bits((r.bits() & !mask) | bits)
+        }),
+        1 => syscfg.exticr2.modify(|r, w| unsafe {
+            w.bits((r.bits() & !mask) | bits)
+        }),
+        2 => syscfg.exticr3.modify(|r, w| unsafe {
+            w.bits((r.bits() & !mask) | bits)
+        }),
+        _ => syscfg.exticr4.modify(|r, w| unsafe {
+            w.bits((r.bits() & !mask) | bits)
+        }),
+    }
+
+    let (rising, falling) = match edge {
+        Edge::Rising => (true, false),
+        Edge::Falling => (false, true),
+        Edge::Both => (true, true),
+    };
+
+    let bit = 1 << line;
+    exti.rtsr1.modify(|r, w| unsafe {
+        w.bits(if rising { r.bits() | bit } else { r.bits() & !bit })
+    });
+    exti.ftsr1.modify(|r, w| unsafe {
+        w.bits(if falling { r.bits() | bit } else { r.bits() & !bit })
+    });
+
+    unmask(line, exti);
+}
+
+/// Unmasks the interrupt of the EXTI `line`
+pub fn unmask(line: u8, exti: &EXTI) {
+    assert!(line < 16);
+
+    exti.imr1.modify(|r, w| unsafe { w.bits(r.bits() | (1 << line)) });
+}
+
+/// Masks the interrupt of the EXTI `line`
+pub fn mask(line: u8, exti: &EXTI) {
+    assert!(line < 16);
+
+    exti.imr1.modify(|r, w| unsafe { w.bits(r.bits() & !(1 << line)) });
+}
+
+/// Clears the pending interrupt of the EXTI `line`
+pub fn clear_pending(line: u8, exti: &EXTI) {
+    assert!(line < 16);
+
+    // writing zero to the other bits has no effect
+    exti.pr1.write(|w| unsafe { w.bits(1 << line) });
+}
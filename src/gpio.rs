@@ -0,0 +1,433 @@
+//! General Purpose Input / Output
+//!
+//! Each pin is modeled as a different type; the type parameter of a pin tracks
+//! the mode the pin is configured in. Use the `split` function of a port
+//! module to get all the pins of a GPIO port, then configure each pin with its
+//! `into_*` methods.
+//!
+//! NOTE The mode changing methods perform read-modify-write operations on
+//! registers shared by all the pins of a port. Don't configure pins of the
+//! same port from contexts that can preempt each other.
+
+use core::marker::PhantomData;
+
+/// Input mode (type state)
+pub struct Input<MODE> {
+    _mode: PhantomData<MODE>,
+}
+
+/// Floating input (type state)
+pub struct Floating;
+
+/// Pulled down input (type state)
+pub struct PullDown;
+
+/// Pulled up input (type state)
+pub struct PullUp;
+
+/// Output mode (type state)
+pub struct Output<MODE> {
+    _mode: PhantomData<MODE>,
+}
+
+/// Push pull output (type state)
+pub struct PushPull;
+
+/// Open drain output (type state)
+pub struct OpenDrain;
+
+/// Internal pull resistor
+#[derive(Clone, Copy, Debug)]
+pub enum Pull {
+    /// No pull resistor
+    None,
+    /// Pull-up resistor
+    Up,
+    /// Pull-down resistor
+    Down,
+}
+
+impl Pull {
+    /// Value of the `PUPDR` field
+    fn pupd(self) -> u32 {
+        match self {
+            Pull::None => 0b00,
+            Pull::Up => 0b01,
+            Pull::Down => 0b10,
+        }
+    }
+}
+
+/// Alternate function modes (type state)
+///
+/// NOTE this trait is implemented for the `AF*` type states; don't implement
+/// it for other types
+pub trait Alternate {}
+
+/// Alternate function 0 (type state)
+///
+/// This is the reset state of the debug pins: PA13, PA14, PA15, PB3 and PB4
+pub struct AF0;
+
+/// Alternate function 1 (type state)
+pub struct AF1;
+
+/// Alternate function 2 (type state)
+pub struct AF2;
+
+/// Alternate function 4 (type state)
+pub struct AF4;
+
+/// Alternate function 5 (type state)
+pub struct AF5;
+
+/// Alternate function 6 (type state)
+pub struct AF6;
+
+/// Alternate function 7 (type state)
+pub struct AF7;
+
+impl Alternate for AF0 {}
+impl Alternate for AF1 {}
+impl Alternate for AF2 {}
+impl Alternate for AF4 {}
+impl Alternate for AF5 {}
+impl Alternate for AF6 {}
+impl Alternate for AF7 {}
+
+macro_rules! gpio {
+    ($GPIOX:ident, $gpiox:ident, $iopxen:ident, [
+        $($PXi:ident: ($pxi:ident, $i:expr, $MODE:ty),)+
+    ]) => {
+        /// GPIO
+        pub mod $gpiox {
+            use core::marker::PhantomData;
+
+            use stm32f30x::{$GPIOX, RCC};
+
+            use super::*;
+
+            /// The pins of this GPIO port
+            pub struct Parts {
+                $(
+                    /// Pin
+                    pub $pxi: $PXi<$MODE>,
+                )+
+            }
+
+            /// Powers up the GPIO port and splits it into independent pins
+            pub fn split(rcc: &RCC) -> Parts {
+                rcc.ahbenr.modify(|_, w| w.$iopxen().set_bit());
+
+                Parts {
+                    $(
+                        $pxi: $PXi { _mode: PhantomData },
+                    )+
+                }
+            }
+
+            fn set_mode(i: u32, mode: u32, otype: u32) {
+                // NOTE(unsafe) see NOTE in the module documentation
+                let gpio = unsafe { &*$GPIOX.get() };
+
+                gpio.moder.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0b11 << (2 * i))) | (mode << (2 * i)))
+                });
+                gpio.otyper.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0b1 << i)) | (otype << i))
+                });
+                // no pull-up or pull-down
+                set_pull(i, 0b00);
+            }
+
+            fn set_pull(i: u32, pupd: u32) {
+                // NOTE(unsafe) see NOTE in the module documentation
+                let gpio = unsafe { &*$GPIOX.get() };
+
+                gpio.pupdr.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0b11 << (2 * i))) | (pupd << (2 * i)))
+                });
+            }
+
+            fn set_alternate(i: u32, af: u32) {
+                // NOTE(unsafe) see NOTE in the module documentation
+                let gpio = unsafe { &*$GPIOX.get() };
+
+                let offset = 4 * (i % 8);
+                let mask = !(0b1111 << offset);
+                if i < 8 {
+                    gpio.afrl.modify(|r, w| unsafe {
+                        w.bits((r.bits() & mask) | (af << offset))
+                    });
+                } else {
+                    gpio.afrh.modify(|r, w| unsafe {
+                        w.bits((r.bits() & mask) | (af << offset))
+                    });
+                }
+
+                set_mode(i, 0b10, 0);
+            }
+
+            $(
+                /// Pin
+                pub struct $PXi<MODE> {
+                    _mode: PhantomData<MODE>,
+                }
+
+                impl<MODE> $PXi<MODE> {
+                    /// Configures the pin to operate as a floating input pin
+                    pub fn into_floating_input(self) -> $PXi<Input<Floating>> {
+                        set_mode($i, 0b00, 0);
+                        $PXi { _mode: PhantomData }
+                    }
+
+                    /// Configures the pin to operate as a pulled down input pin
+                    pub fn into_pull_down_input(self) -> $PXi<Input<PullDown>> {
+                        set_mode($i, 0b00, 0);
+                        set_pull($i, 0b10);
+                        $PXi { _mode: PhantomData }
+                    }
+
+                    /// Configures the pin to operate as a pulled up input pin
+                    pub fn into_pull_up_input(self) -> $PXi<Input<PullUp>> {
+                        set_mode($i, 0b00, 0);
+                        set_pull($i, 0b01);
+                        $PXi { _mode: PhantomData }
+                    }
+
+                    /// Configures the pin to operate as a push pull output
+                    /// pin
+                    pub fn into_push_pull_output(
+                        self,
+                    ) -> $PXi<Output<PushPull>> {
+                        set_mode($i, 0b01, 0);
+                        $PXi { _mode: PhantomData }
+                    }
+
+                    /// Configures the pin to operate as an open drain output
+                    /// pin
+                    pub fn into_open_drain_output(
+                        self,
+                    ) -> $PXi<Output<OpenDrain>> {
+                        set_mode($i, 0b01, 1);
+                        $PXi { _mode: PhantomData }
+                    }
+
+                    /// Configures the pin to serve as alternate function 1
+                    pub fn into_alternate_af1(self) -> $PXi<AF1> {
+                        set_alternate($i, 1);
+                        $PXi { _mode: PhantomData }
+                    }
+
+                    /// Configures the pin to serve as alternate function 2
+                    pub fn into_alternate_af2(self) -> $PXi<AF2> {
+                        set_alternate($i, 2);
+                        $PXi { _mode: PhantomData }
+                    }
+
+                    /// Configures the pin to serve as alternate function 4
+                    pub fn into_alternate_af4(self) -> $PXi<AF4> {
+                        set_alternate($i, 4);
+                        $PXi { _mode: PhantomData }
+                    }
+
+                    /// Configures the pin to serve as alternate function 5
+                    pub fn into_alternate_af5(self) -> $PXi<AF5> {
+                        set_alternate($i, 5);
+                        $PXi { _mode: PhantomData }
+                    }
+
+                    /// Configures the pin to serve as alternate function 6
+                    pub fn into_alternate_af6(self) -> $PXi<AF6> {
+                        set_alternate($i, 6);
+                        $PXi { _mode: PhantomData }
+                    }
+
+                    /// Configures the pin to serve as alternate function 7
+                    pub fn into_alternate_af7(self) -> $PXi<AF7> {
+                        set_alternate($i, 7);
+                        $PXi { _mode: PhantomData }
+                    }
+                }
+
+                impl<MODE> $PXi<Output<MODE>> {
+                    /// Enables or disables the internal pull resistor
+                    ///
+                    /// This is meant for open drain outputs that don't have an
+                    /// external pull resistor. The mode changing methods
+                    /// disable the resistor so call this after them
+                    pub fn internal_pull(&self, pull: Pull) {
+                        set_pull($i, pull.pupd());
+                    }
+
+                    /// Drives the pin high
+                    pub fn set_high(&self) {
+                        // NOTE(safe) atomic write
+                        unsafe {
+                            (*$GPIOX.get()).bsrr.write(|w| w.bits(1 << $i))
+                        }
+                    }
+
+                    /// Drives the pin low
+                    pub fn set_low(&self) {
+                        // NOTE(safe) atomic write
+                        unsafe {
+                            (*$GPIOX.get())
+                                .bsrr
+                                .write(|w| w.bits(1 << (16 + $i)))
+                        }
+                    }
+
+                    /// Returns `true` if the pin is being driven high
+                    pub fn is_set_high(&self) -> bool {
+                        // NOTE(safe) atomic read
+                        unsafe {
+                            (*$GPIOX.get()).odr.read().bits() & (1 << $i) != 0
+                        }
+                    }
+
+                    /// Returns `true` if the pin is being driven low
+                    pub fn is_set_low(&self) -> bool {
+                        !self.is_set_high()
+                    }
+                }
+
+                impl<AF> $PXi<AF>
+                where
+                    AF: Alternate,
+                {
+                    /// Enables or disables the internal pull resistor
+                    ///
+                    /// The mode changing methods disable the resistor so call
+                    /// this after them. This is meant for alternate function
+                    /// pins that may be left floating, e.g. an SPI MISO pin
+                    /// when no slave is driving the line; skip it if the board
+                    /// already has external pull resistors
+                    pub fn internal_pull(&self, pull: Pull) {
+                        set_pull($i, pull.pupd());
+                    }
+                }
+
+                impl<MODE> $PXi<Input<MODE>> {
+                    /// Returns `true` if the pin is at a high logic level
+                    pub fn is_high(&self) -> bool {
+                        // NOTE(safe) atomic read
+                        unsafe {
+                            (*$GPIOX.get()).idr.read().bits() & (1 << $i) != 0
+                        }
+                    }
+
+                    /// Returns `true` if the pin is at a low logic level
+                    pub fn is_low(&self) -> bool {
+                        !self.is_high()
+                    }
+                }
+            )+
+        }
+    }
+}
+
+gpio!(GPIOA, gpioa, iopaen, [
+    PA0: (pa0, 0, Input<Floating>),
+    PA1: (pa1, 1, Input<Floating>),
+    PA2: (pa2, 2, Input<Floating>),
+    PA3: (pa3, 3, Input<Floating>),
+    PA4: (pa4, 4, Input<Floating>),
+    PA5: (pa5, 5, Input<Floating>),
+    PA6: (pa6, 6, Input<Floating>),
+    PA7: (pa7, 7, Input<Floating>),
+    PA8: (pa8, 8, Input<Floating>),
+    PA9: (pa9, 9, Input<Floating>),
+    PA10: (pa10, 10, Input<Floating>),
+    PA11: (pa11, 11, Input<Floating>),
+    PA12: (pa12, 12, Input<Floating>),
+    PA13: (pa13, 13, AF0),
+    PA14: (pa14, 14, AF0),
+    PA15: (pa15, 15, AF0),
+]);
+
+gpio!(GPIOB, gpiob, iopben, [
+    PB0: (pb0, 0, Input<Floating>),
+    PB1: (pb1, 1, Input<Floating>),
+    PB2: (pb2, 2, Input<Floating>),
+    PB3: (pb3, 3, AF0),
+    PB4: (pb4, 4, AF0),
+    PB5: (pb5, 5, Input<Floating>),
+    PB6: (pb6, 6, Input<Floating>),
+    PB7: (pb7, 7, Input<Floating>),
+    PB8: (pb8, 8, Input<Floating>),
+    PB9: (pb9, 9, Input<Floating>),
+    PB10: (pb10, 10, Input<Floating>),
+    PB11: (pb11, 11, Input<Floating>),
+    PB12: (pb12, 12, Input<Floating>),
+    PB13: (pb13, 13, Input<Floating>),
+    PB14: (pb14, 14, Input<Floating>),
+    PB15: (pb15, 15, Input<Floating>),
+]);
+
+gpio!(GPIOC, gpioc, iopcen, [
+    PC0: (pc0, 0, Input<Floating>),
+    PC1: (pc1, 1, Input<Floating>),
+    PC2: (pc2, 2, Input<Floating>),
+    PC3: (pc3, 3, Input<Floating>),
+    PC4: (pc4, 4, Input<Floating>),
+    PC5: (pc5, 5, Input<Floating>),
+    PC6: (pc6, 6, Input<Floating>),
+    PC7: (pc7, 7, Input<Floating>),
+    PC8: (pc8, 8, Input<Floating>),
+    PC9: (pc9, 9, Input<Floating>),
+    PC10: (pc10, 10, Input<Floating>),
+    PC11: (pc11, 11, Input<Floating>),
+    PC12: (pc12, 12, Input<Floating>),
+    PC13: (pc13, 13, Input<Floating>),
+    PC14: (pc14, 14, Input<Floating>),
+    PC15: (pc15, 15, Input<Floating>),
+]);
+
+gpio!(GPIOD, gpiod, iopden, [
+    PD0: (pd0, 0, Input<Floating>),
+    PD1: (pd1, 1, Input<Floating>),
+    PD2: (pd2, 2, Input<Floating>),
+    PD3: (pd3, 3, Input<Floating>),
+    PD4: (pd4, 4, Input<Floating>),
+    PD5: (pd5, 5, Input<Floating>),
+    PD6: (pd6, 6, Input<Floating>),
+    PD7: (pd7, 7, Input<Floating>),
+    PD8: (pd8, 8, Input<Floating>),
+    PD9: (pd9, 9, Input<Floating>),
+    PD10: (pd10, 10, Input<Floating>),
+    PD11: (pd11, 11, Input<Floating>),
+    PD12: (pd12, 12, Input<Floating>),
+    PD13: (pd13, 13, Input<Floating>),
+    PD14: (pd14, 14, Input<Floating>),
+    PD15: (pd15, 15, Input<Floating>),
+]);
+
+gpio!(GPIOE, gpioe, iopeen, [
+    PE0: (pe0, 0, Input<Floating>),
+    PE1: (pe1, 1, Input<Floating>),
+    PE2: (pe2, 2, Input<Floating>),
+    PE3: (pe3, 3, Input<Floating>),
+    PE4: (pe4, 4, Input<Floating>),
+    PE5: (pe5, 5, Input<Floating>),
+    PE6: (pe6, 6, Input<Floating>),
+    PE7: (pe7, 7, Input<Floating>),
+    PE8: (pe8, 8, Input<Floating>),
+    PE9: (pe9, 9, Input<Floating>),
+    PE10: (pe10, 10, Input<Floating>),
+    PE11: (pe11, 11, Input<Floating>),
+    PE12: (pe12, 12, Input<Floating>),
+    PE13: (pe13, 13, Input<Floating>),
+    PE14: (pe14, 14, Input<Floating>),
+    PE15: (pe15, 15, Input<Floating>),
+]);
+
+gpio!(GPIOF, gpiof, iopfen, [
+    PF0: (pf0, 0, Input<Floating>),
+    PF1: (pf1, 1, Input<Floating>),
+    PF2: (pf2, 2, Input<Floating>),
+    PF4: (pf4, 4, Input<Floating>),
+    PF6: (pf6, 6, Input<Floating>),
+    PF9: (pf9, 9, Input<Floating>),
+    PF10: (pf10, 10, Input<Floating>),
+]);
@@ -1,8 +1,16 @@
 //! User LEDs
 
+use core::cell::Cell;
+
+use cast::{u32, usize};
 use stm32f30x::{GPIOE, RCC};
 
+use timer::Timer;
+
 /// All the user LEDs
+///
+/// `LEDS[0]` is LD3 (North); the rest follow clockwise. Prefer the `Leds`
+/// handles returned by `init`
 pub static LEDS: [Led; 8] = [
     Led { i: 9 },
     Led { i: 10 },
@@ -20,6 +28,12 @@ pub struct Led {
 }
 
 impl Led {
+    /// Returns `true` if the LED is on
+    pub fn is_on(&self) -> bool {
+        // NOTE(safe) atomic read
+        unsafe { (*GPIOE.get()).odr.read().bits() & (1 << self.i) != 0 }
+    }
+
     /// Turns off the LED
     pub fn off(&self) {
         // NOTE(safe) atomic write
@@ -31,10 +45,285 @@ impl Led {
         // NOTE(safe) atomic write
         unsafe { (*GPIOE.get()).bsrr.write(|w| w.bits(1 << self.i)) }
     }
+
+    /// Drives the LED pin high, turning on the LED
+    pub fn set_high(&self) {
+        self.on()
+    }
+
+    /// Drives the LED pin low, turning off the LED
+    pub fn set_low(&self) {
+        self.off()
+    }
+
+    /// Toggles the LED
+    ///
+    /// NOTE this is a read-modify-write operation; don't toggle the same LED
+    /// from contexts that can preempt each other
+    pub fn toggle(&self) {
+        if self.is_on() {
+            self.off()
+        } else {
+            self.on()
+        }
+    }
+}
+
+/// Blink pattern of a `Heartbeat`
+#[derive(Clone, Copy, Debug)]
+pub enum Pattern {
+    /// The LED is toggled on every tick
+    Blink,
+    /// Two short blinks followed by a pause; takes 8 ticks
+    DoubleBlink,
+}
+
+/// LED states (on = `true`) of the `DoubleBlink` pattern, one per tick
+const DOUBLE_BLINK: [bool; 8] =
+    [true, false, true, false, false, false, false, false];
+
+/// A status LED that signals that the program is alive
+///
+/// Call `tick` from a periodic task (e.g. a timer interrupt); the
+/// `Heartbeat` keeps track of the phase of the blink pattern. A `Blink`
+/// pattern ticked at 2 Hz gives the usual 1 Hz blink.
+///
+/// # Low power operation
+///
+/// Driving the heartbeat from the update interrupt of a `Timer` (see
+/// `on_timeout`) lets the processor sleep (`wfi`) between LED updates instead
+/// of busy waiting; the timer wakes it up for each step of the pattern.
+pub struct Heartbeat {
+    led: &'static Led,
+    pattern: Pattern,
+    phase: Cell<usize>,
+}
+
+impl Heartbeat {
+    /// Creates a heartbeat that blinks `led` using `pattern`
+    pub const fn new(led: &'static Led, pattern: Pattern) -> Self {
+        Heartbeat {
+            led: led,
+            pattern: pattern,
+            phase: Cell::new(0),
+        }
+    }
+
+    /// Services the update interrupt of `timer`
+    ///
+    /// Clears the timer update flag and advances the blink pattern by one
+    /// step. Returns `false`, doing nothing, if the timer hasn't timed out
+    pub fn on_timeout(&self, timer: &Timer) -> bool {
+        if timer.clear_update_flag().is_ok() {
+            self.tick();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Advances the blink pattern by one step
+    pub fn tick(&self) {
+        match self.pattern {
+            Pattern::Blink => self.led.toggle(),
+            Pattern::DoubleBlink => {
+                let phase = self.phase.get();
+
+                if DOUBLE_BLINK[phase] {
+                    self.led.on()
+                } else {
+                    self.led.off()
+                }
+
+                self.phase.set((phase + 1) % DOUBLE_BLINK.len());
+            }
+        }
+    }
+}
+
+impl Default for Heartbeat {
+    /// A `Blink` heartbeat on LD3 (north), `LEDS[0]`
+    fn default() -> Self {
+        Heartbeat::new(&LEDS[0], Pattern::Blink)
+    }
+}
+
+/// Gamma correction table (gamma = 2.8), see `gamma`
+static GAMMA: [u16; 256] = [
+    0, 0, 0, 0, 1, 1, 2, 3,
+    4, 6, 8, 10, 13, 16, 19, 24,
+    28, 33, 39, 46, 53, 60, 69, 78,
+    88, 98, 110, 122, 135, 149, 164, 179,
+    196, 214, 232, 252, 273, 295, 317, 341,
+    366, 393, 420, 449, 478, 510, 542, 575,
+    610, 647, 684, 723, 764, 806, 849, 894,
+    940, 988, 1037, 1088, 1140, 1194, 1250, 1307,
+    1366, 1427, 1489, 1553, 1619, 1686, 1756, 1827,
+    1900, 1975, 2051, 2130, 2210, 2293, 2377, 2463,
+    2552, 2642, 2734, 2829, 2925, 3024, 3124, 3227,
+    3332, 3439, 3548, 3660, 3774, 3890, 4008, 4128,
+    4251, 4376, 4504, 4634, 4766, 4901, 5038, 5177,
+    5319, 5464, 5611, 5760, 5912, 6067, 6224, 6384,
+    6546, 6711, 6879, 7049, 7222, 7397, 7576, 7757,
+    7941, 8128, 8317, 8509, 8704, 8902, 9103, 9307,
+    9514, 9723, 9936, 10151, 10370, 10591, 10816, 11043,
+    11274, 11507, 11744, 11984, 12227, 12473, 12722, 12975,
+    13230, 13489, 13751, 14017, 14285, 14557, 14833, 15111,
+    15393, 15678, 15967, 16259, 16554, 16853, 17155, 17461,
+    17770, 18083, 18399, 18719, 19042, 19369, 19700, 20034,
+    20372, 20713, 21058, 21407, 21759, 22115, 22475, 22838,
+    23206, 23577, 23952, 24330, 24713, 25099, 25489, 25884,
+    26282, 26683, 27089, 27499, 27913, 28330, 28752, 29178,
+    29608, 30041, 30479, 30921, 31367, 31818, 32272, 32730,
+    33193, 33660, 34131, 34606, 35085, 35569, 36057, 36549,
+    37046, 37547, 38052, 38561, 39075, 39593, 40116, 40643,
+    41175, 41711, 42251, 42796, 43346, 43899, 44458, 45021,
+    45588, 46161, 46737, 47319, 47905, 48495, 49091, 49691,
+    50295, 50905, 51519, 52138, 52761, 53390, 54023, 54661,
+    55303, 55951, 56604, 57261, 57923, 58590, 59262, 59939,
+    60621, 61308, 62000, 62697, 63399, 64106, 64818, 65535,
+];
+
+/// Maps a perceptual `brightness` (0 - 255) to a PWM duty cycle (0 - 65535)
+///
+/// The eye responds non-linearly to light so linear changes in duty cycle
+/// don't look like linear changes in brightness; this corrects for that. To
+/// get the duty cycle for a PWM with a maximum duty of `max` compute
+/// `u32(gamma(brightness)) * u32(max) / 65535`
+pub fn gamma(brightness: u8) -> u16 {
+    GAMMA[usize(brightness)]
 }
 
-/// Initializes all the user LEDs
-pub fn init(gpioe: &GPIOE, rcc: &RCC) {
+/// Playback mode of an `Animator`
+#[derive(Clone, Copy, Debug)]
+pub enum Mode {
+    /// Start over after the last frame
+    Loop,
+    /// Stop at the last frame
+    OneShot,
+}
+
+/// A single LED on at a time, going around the compass clockwise
+pub static SPINNER: [u8; 8] = [
+    0b0000_0001,
+    0b0000_0010,
+    0b0000_0100,
+    0b0000_1000,
+    0b0001_0000,
+    0b0010_0000,
+    0b0100_0000,
+    0b1000_0000,
+];
+
+/// The LEDs turn on one after the other, going around the compass clockwise,
+/// and then all turn off
+pub static FILL: [u8; 9] = [
+    0b0000_0001,
+    0b0000_0011,
+    0b0000_0111,
+    0b0000_1111,
+    0b0001_1111,
+    0b0011_1111,
+    0b0111_1111,
+    0b1111_1111,
+    0b0000_0000,
+];
+
+/// LED animation player
+///
+/// Each frame is a bitmask where bit `i` is the state of `LEDS[i]` (set = on).
+/// Call `step` from a periodic task (e.g. a timer interrupt) to advance the
+/// animation by one frame.
+pub struct Animator {
+    frames: &'static [u8],
+    mode: Mode,
+    next: Cell<usize>,
+}
+
+impl Animator {
+    /// Creates an animator that plays `frames`
+    pub const fn new(frames: &'static [u8], mode: Mode) -> Self {
+        Animator {
+            frames: frames,
+            mode: mode,
+            next: Cell::new(0),
+        }
+    }
+
+    /// Displays the next frame
+    ///
+    /// Returns `false` if a `OneShot` animation has already displayed all its
+    /// frames; in that case the LEDs are left untouched
+    pub fn step(&self) -> bool {
+        let mut i = self.next.get();
+
+        if i == self.frames.len() {
+            match self.mode {
+                Mode::OneShot => return false,
+                Mode::Loop => i = 0,
+            }
+        }
+
+        if let Some(frame) = self.frames.get(i) {
+            set_mask(*frame);
+
+            self.next.set(i + 1);
+        }
+
+        true
+    }
+
+    /// Restarts the animation from its first frame
+    pub fn reset(&self) {
+        self.next.set(0)
+    }
+}
+
+/// Turns on all the LEDs
+pub fn all_on() {
+    set_mask(0xff)
+}
+
+/// Turns off all the LEDs
+pub fn all_off() {
+    set_mask(0x00)
+}
+
+/// Sets the state of all the LEDs at once
+///
+/// Bit `i` of `mask` is the state of `LEDS[i]` (set = on). All the LEDs are
+/// updated with a single register write so there's no visible stagger
+pub fn set_mask(mask: u8) {
+    // `LEDS[i]` is pin `i + 9`, except for `LEDS[7]` which is pin 8
+    let on = u32(mask.rotate_left(1)) << 8;
+    let off = !on & 0xff00;
+
+    // NOTE(safe) atomic write
+    unsafe { (*GPIOE.get()).bsrr.write(|w| w.bits(on | (off << 16))) }
+}
+
+/// Owned handles to the user LEDs, named after their compass direction
+pub struct Leds {
+    /// LD3 (PE9)
+    pub n: Led,
+    /// LD5 (PE10)
+    pub ne: Led,
+    /// LD7 (PE11)
+    pub e: Led,
+    /// LD9 (PE12)
+    pub se: Led,
+    /// LD10 (PE13)
+    pub s: Led,
+    /// LD8 (PE14)
+    pub sw: Led,
+    /// LD6 (PE15)
+    pub w: Led,
+    /// LD4 (PE8)
+    pub nw: Led,
+}
+
+/// Initializes all the user LEDs and returns handles to them
+pub fn init(gpioe: &GPIOE, rcc: &RCC) -> Leds {
     // Power up peripherals
     rcc.ahbenr.modify(|_, w| w.iopeen().enabled());
 
@@ -61,4 +350,15 @@ pub fn init(gpioe: &GPIOE, rcc: &RCC) {
                     .output()
             },
         );
+
+    Leds {
+        n: Led { i: 9 },
+        ne: Led { i: 10 },
+        e: Led { i: 11 },
+        se: Led { i: 12 },
+        s: Led { i: 13 },
+        sw: Led { i: 14 },
+        w: Led { i: 15 },
+        nw: Led { i: 8 },
+    }
 }
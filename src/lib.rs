@@ -14,6 +14,26 @@
 //! Check the [examples] module.
 //!
 //! [examples]: ./examples/index.html
+//!
+//! # Blocking
+//!
+//! The non-blocking methods of this crate return `nb::Result`. The `block!`
+//! macro, re-exported from the `nb` crate, turns any of them into a blocking
+//! call by retrying the operation until it stops returning `WouldBlock`:
+//!
+//! ``` ignore
+//! #[macro_use]
+//! extern crate f3;
+//!
+//! // wait for a byte
+//! let byte = block!(serial.read())?;
+//!
+//! // wait for the DMA to release the buffer
+//! block!(buffer.release(&dma1))?;
+//!
+//! // wait for the next timeout; this can't fail
+//! block!(timer.wait()).unwrap();
+//! ```
 
 #![deny(missing_docs)]
 #![deny(warnings)]
@@ -21,12 +41,17 @@
 #![feature(const_unsafe_cell_new)]
 #![feature(const_cell_new)]
 #![feature(get_type_id)]
+#![feature(macro_reexport)]
 #![feature(never_type)]
+#![feature(non_exhaustive)]
 #![feature(unsize)]
 #![no_std]
 
 extern crate cast;
+extern crate cortex_m;
 extern crate embedded_hal as hal;
+#[macro_reexport(block)]
+#[macro_use]
 extern crate nb;
 extern crate static_ref;
 
@@ -35,15 +60,30 @@ pub extern crate stm32f30x;
 // For documentation only
 pub mod examples;
 
+pub mod adc;
+pub mod button;
+pub mod capture;
+pub mod crc;
+pub mod delay;
 pub mod dma;
+pub mod exti;
+pub mod gpio;
 pub mod led;
+pub mod pwm;
+pub mod qei;
+pub mod rcc;
+pub mod ring;
+pub mod rtc;
+pub mod sampler;
 pub mod serial;
 pub mod timer;
 pub mod time;
+pub mod vector;
 
 pub mod frequency;
 use frequency::*;
 
 pub use hal::prelude;
 pub use serial::Serial;
+pub use vector::{I16x2, I16x3};
 
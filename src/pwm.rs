@@ -0,0 +1,558 @@
+//! Pulse Width Modulation
+//!
+//! You can use the `Pwm` interface with these TIM instances
+//!
+//! # TIM3
+//!
+//! - CH1 = PA6
+//! - CH2 = PA7
+//! - CH3 = PB0
+//! - CH4 = PB1
+//!
+//! All these pins are broken out on the pin headers of the STM32F3DISCOVERY
+//!
+//! # TIM1
+//!
+//! - CH1 = PE9, CH1N = PE8
+//! - CH2 = PE11, CH2N = PE10
+//! - CH3 = PE13, CH3N = PE12
+//! - CH4 = PE14 (no complementary output)
+//!
+//! NOTE these pins are also connected to the user LEDs; don't use the `led`
+//! module together with this timer
+//!
+//! Channels 1 to 3 drive a pair of complementary outputs: the CHxN output is
+//! the inverse of the CHx output with a *dead time* inserted after every edge
+//! during which both outputs are inactive. This is what a half-bridge stage
+//! needs to avoid shoot-through.
+//!
+//! The break input is left disabled. If it were enabled, an active level on
+//! it would asynchronously clear the main output enable (`BDTR.MOE`) putting
+//! all the outputs in their inactive state until software sets `MOE` again.
+
+use core::u16;
+
+use cast::{u16, u32, u8};
+use hal;
+use stm32f30x::{GPIOA, GPIOB, GPIOE, RCC, TIM1, TIM3};
+
+/// Specialized `Result` type
+pub type Result<T> = ::core::result::Result<T, Error>;
+
+/// An error
+#[derive(Debug)]
+pub struct Error {
+    _0: (),
+}
+
+/// PWM channel
+#[derive(Clone, Copy, Debug)]
+pub enum Channel {
+    /// TIMx_CH1
+    _1,
+    /// TIMx_CH2
+    _2,
+    /// TIMx_CH3
+    _3,
+    /// TIMx_CH4
+    _4,
+}
+
+/// PWM driver
+pub struct Pwm<'a, T>(pub &'a T)
+where
+    T: 'a;
+
+impl<'a> Pwm<'a, TIM3> {
+    /// Initializes the PWM module with a period of `period`
+    ///
+    /// All the channels will be configured in PWM mode 1 (active while the
+    /// counter is below the duty cycle) with a duty cycle of zero
+    ///
+    /// Returns `Err` if the period is shorter than 2 ticks or longer than
+    /// what the timer can count (`2^16 * (2^16 - 1)` ticks)
+    ///
+    /// NOTE After initialization, all the channels will be disabled.
+    pub fn init<P>(
+        &self,
+        period: P,
+        gpioa: &GPIOA,
+        gpiob: &GPIOB,
+        rcc: &RCC,
+    ) -> Result<()>
+    where
+        P: Into<::apb1::Ticks>,
+    {
+        self._init(period.into(), gpioa, gpiob, rcc)
+    }
+
+    fn _init(
+        &self,
+        period: ::apb1::Ticks,
+        gpioa: &GPIOA,
+        gpiob: &GPIOB,
+        rcc: &RCC,
+    ) -> Result<()> {
+        let tim3 = self.0;
+
+        // power up peripherals
+        rcc.apb1enr.modify(|_, w| w.tim3en().enabled());
+        rcc.ahbenr.modify(|_, w| w.iopaen().set_bit().iopben().set_bit());
+
+        // PA6 = CH1, PA7 = CH2
+        gpioa.afrl.modify(|_, w| unsafe { w.afrl6().bits(2).afrl7().bits(2) });
+        gpioa.moder.modify(|_, w| w.moder6().alternate().moder7().alternate());
+
+        // PB0 = CH3, PB1 = CH4
+        gpiob.afrl.modify(|_, w| unsafe { w.afrl0().bits(2).afrl1().bits(2) });
+        gpiob.moder.modify(|_, w| w.moder0().alternate().moder1().alternate());
+
+        // PWM mode 1 with preload
+        tim3.ccmr1_output.write(|w| unsafe {
+            w.oc1pe()
+                .set_bit()
+                .oc1m()
+                .bits(0b110)
+                .oc2pe()
+                .set_bit()
+                .oc2m()
+                .bits(0b110)
+        });
+        tim3.ccmr2_output.write(|w| unsafe {
+            w.oc3pe()
+                .set_bit()
+                .oc3m()
+                .bits(0b110)
+                .oc4pe()
+                .set_bit()
+                .oc4m()
+                .bits(0b110)
+        });
+
+        // active high; all channels disabled
+        tim3.ccer.write(|w| {
+            w.cc1p()
+                .clear_bit()
+                .cc1e()
+                .clear_bit()
+                .cc2p()
+                .clear_bit()
+                .cc2e()
+                .clear_bit()
+                .cc3p()
+                .clear_bit()
+                .cc3e()
+                .clear_bit()
+                .cc4p()
+                .clear_bit()
+                .cc4e()
+                .clear_bit()
+        });
+
+        tim3.ccr1.write(|w| unsafe { w.bits(0) });
+        tim3.ccr2.write(|w| unsafe { w.bits(0) });
+        tim3.ccr3.write(|w| unsafe { w.bits(0) });
+        tim3.ccr4.write(|w| unsafe { w.bits(0) });
+
+        self._set_period(period)?;
+
+        // edge aligned, up counting, auto-reload preload
+        tim3.cr1.write(|w| unsafe {
+            w.cms()
+                .bits(0b00)
+                .dir()
+                .clear_bit()
+                .opm()
+                .clear_bit()
+                .arpe()
+                .set_bit()
+                .cen()
+                .set_bit()
+        });
+
+        Ok(())
+    }
+
+    fn _set_period(&self, period: ::apb1::Ticks) -> Result<()> {
+        let tim3 = self.0;
+
+        let ratio = period.0;
+        if ratio < 2 {
+            return Err(Error { _0: () });
+        }
+
+        let psc = u16((ratio - 1) / u32(u16::MAX))
+            .map_err(|_| Error { _0: () })?;
+        tim3.psc.write(|w| w.psc().bits(psc));
+        let arr = u16(ratio / (u32(psc) + 1)).map_err(|_| Error { _0: () })?;
+        tim3.arr.write(|w| unsafe { w.bits(u32(arr)) });
+
+        // load the new prescaler and auto-reload values
+        tim3.egr.write(|w| w.ug().set_bit());
+
+        Ok(())
+    }
+}
+
+impl<'a> hal::Pwm for Pwm<'a, TIM3> {
+    type Channel = Channel;
+    type Duty = u16;
+    type Time = ::apb1::Ticks;
+
+    fn disable(&self, channel: Channel) {
+        let ccer = &self.0.ccer;
+
+        match channel {
+            Channel::_1 => ccer.modify(|_, w| w.cc1e().clear_bit()),
+            Channel::_2 => ccer.modify(|_, w| w.cc2e().clear_bit()),
+            Channel::_3 => ccer.modify(|_, w| w.cc3e().clear_bit()),
+            Channel::_4 => ccer.modify(|_, w| w.cc4e().clear_bit()),
+        }
+    }
+
+    fn enable(&self, channel: Channel) {
+        let ccer = &self.0.ccer;
+
+        match channel {
+            Channel::_1 => ccer.modify(|_, w| w.cc1e().set_bit()),
+            Channel::_2 => ccer.modify(|_, w| w.cc2e().set_bit()),
+            Channel::_3 => ccer.modify(|_, w| w.cc3e().set_bit()),
+            Channel::_4 => ccer.modify(|_, w| w.cc4e().set_bit()),
+        }
+    }
+
+    fn get_duty(&self, channel: Channel) -> u16 {
+        let tim3 = self.0;
+
+        let ccr = match channel {
+            Channel::_1 => tim3.ccr1.read().bits(),
+            Channel::_2 => tim3.ccr2.read().bits(),
+            Channel::_3 => tim3.ccr3.read().bits(),
+            Channel::_4 => tim3.ccr4.read().bits(),
+        };
+
+        u16(ccr).unwrap()
+    }
+
+    fn get_max_duty(&self) -> u16 {
+        u16(self.0.arr.read().bits()).unwrap()
+    }
+
+    fn get_period(&self) -> ::apb1::Ticks {
+        let tim3 = self.0;
+
+        let psc = u32(tim3.psc.read().psc().bits());
+        let arr = tim3.arr.read().bits();
+
+        ::apb1::Ticks((psc + 1) * arr)
+    }
+
+    fn set_duty(&self, channel: Channel, duty: u16) {
+        let tim3 = self.0;
+
+        match channel {
+            Channel::_1 => tim3.ccr1.write(|w| unsafe { w.bits(u32(duty)) }),
+            Channel::_2 => tim3.ccr2.write(|w| unsafe { w.bits(u32(duty)) }),
+            Channel::_3 => tim3.ccr3.write(|w| unsafe { w.bits(u32(duty)) }),
+            Channel::_4 => tim3.ccr4.write(|w| unsafe { w.bits(u32(duty)) }),
+        }
+    }
+
+    fn set_period<P>(&self, period: P)
+    where
+        P: Into<::apb1::Ticks>,
+    {
+        self._set_period(period.into()).unwrap()
+    }
+}
+
+impl<'a> Pwm<'a, TIM1> {
+    /// Initializes the PWM module with a period of `period` and a dead time
+    /// of `dead_time` between complementary outputs
+    ///
+    /// All the channels will be configured in PWM mode 1 (active while the
+    /// counter is below the duty cycle) with a duty cycle of zero
+    ///
+    /// Returns `Err` if the period is shorter than 2 ticks or longer than
+    /// what the timer can count (`2^16 * (2^16 - 1)` ticks)
+    ///
+    /// NOTE After initialization, all the channels will be disabled.
+    ///
+    /// # Panics
+    ///
+    /// If `dead_time` is longer than 1008 ticks
+    pub fn init<P, D>(
+        &self,
+        period: P,
+        dead_time: D,
+        gpioe: &GPIOE,
+        rcc: &RCC,
+    ) -> Result<()>
+    where
+        P: Into<::apb2::Ticks>,
+        D: Into<::apb2::Ticks>,
+    {
+        self._init(period.into(), dead_time.into(), gpioe, rcc)
+    }
+
+    fn _init(
+        &self,
+        period: ::apb2::Ticks,
+        dead_time: ::apb2::Ticks,
+        gpioe: &GPIOE,
+        rcc: &RCC,
+    ) -> Result<()> {
+        let tim1 = self.0;
+
+        // power up peripherals
+        rcc.apb2enr.modify(|_, w| w.tim1en().enabled());
+        rcc.ahbenr.modify(|_, w| w.iopeen().enabled());
+
+        // PE8 = CH1N, PE9 = CH1, PE10 = CH2N, PE11 = CH2, PE12 = CH3N,
+        // PE13 = CH3, PE14 = CH4
+        gpioe.afrh.modify(|_, w| unsafe {
+            w.afrh8()
+                .bits(2)
+                .afrh9()
+                .bits(2)
+                .afrh10()
+                .bits(2)
+                .afrh11()
+                .bits(2)
+                .afrh12()
+                .bits(2)
+                .afrh13()
+                .bits(2)
+                .afrh14()
+                .bits(2)
+        });
+        gpioe.moder.modify(|_, w| {
+            w.moder8()
+                .alternate()
+                .moder9()
+                .alternate()
+                .moder10()
+                .alternate()
+                .moder11()
+                .alternate()
+                .moder12()
+                .alternate()
+                .moder13()
+                .alternate()
+                .moder14()
+                .alternate()
+        });
+
+        // PWM mode 1 with preload
+        tim1.ccmr1_output.write(|w| unsafe {
+            w.oc1pe()
+                .set_bit()
+                .oc1m()
+                .bits(0b110)
+                .oc2pe()
+                .set_bit()
+                .oc2m()
+                .bits(0b110)
+        });
+        tim1.ccmr2_output.write(|w| unsafe {
+            w.oc3pe()
+                .set_bit()
+                .oc3m()
+                .bits(0b110)
+                .oc4pe()
+                .set_bit()
+                .oc4m()
+                .bits(0b110)
+        });
+
+        // active high; all channels disabled
+        tim1.ccer.write(|w| {
+            w.cc1p()
+                .clear_bit()
+                .cc1np()
+                .clear_bit()
+                .cc1e()
+                .clear_bit()
+                .cc1ne()
+                .clear_bit()
+                .cc2p()
+                .clear_bit()
+                .cc2np()
+                .clear_bit()
+                .cc2e()
+                .clear_bit()
+                .cc2ne()
+                .clear_bit()
+                .cc3p()
+                .clear_bit()
+                .cc3np()
+                .clear_bit()
+                .cc3e()
+                .clear_bit()
+                .cc3ne()
+                .clear_bit()
+                .cc4p()
+                .clear_bit()
+                .cc4e()
+                .clear_bit()
+        });
+
+        tim1.ccr1.write(|w| unsafe { w.bits(0) });
+        tim1.ccr2.write(|w| unsafe { w.bits(0) });
+        tim1.ccr3.write(|w| unsafe { w.bits(0) });
+        tim1.ccr4.write(|w| unsafe { w.bits(0) });
+
+        self._set_period(period)?;
+
+        // dtg: dead time
+        // bke: break input disabled
+        // moe: main output enabled
+        let dtg = dtg(dead_time.0);
+        tim1.bdtr.write(|w| unsafe {
+            w.dtg().bits(dtg).bke().clear_bit().moe().set_bit()
+        });
+
+        // edge aligned, up counting, auto-reload preload
+        tim1.cr1.write(|w| unsafe {
+            w.cms()
+                .bits(0b00)
+                .dir()
+                .clear_bit()
+                .opm()
+                .clear_bit()
+                .arpe()
+                .set_bit()
+                .cen()
+                .set_bit()
+        });
+
+        Ok(())
+    }
+
+    fn _set_period(&self, period: ::apb2::Ticks) -> Result<()> {
+        let tim1 = self.0;
+
+        let ratio = period.0;
+        if ratio < 2 {
+            return Err(Error { _0: () });
+        }
+
+        let psc = u16((ratio - 1) / u32(u16::MAX))
+            .map_err(|_| Error { _0: () })?;
+        tim1.psc.write(|w| w.psc().bits(psc));
+        let arr = u16(ratio / (u32(psc) + 1)).map_err(|_| Error { _0: () })?;
+        tim1.arr.write(|w| unsafe { w.bits(u32(arr)) });
+
+        // load the new prescaler and auto-reload values
+        tim1.egr.write(|w| w.ug().set_bit());
+
+        Ok(())
+    }
+}
+
+impl<'a> hal::Pwm for Pwm<'a, TIM1> {
+    type Channel = Channel;
+    type Duty = u16;
+    type Time = ::apb2::Ticks;
+
+    fn disable(&self, channel: Channel) {
+        let ccer = &self.0.ccer;
+
+        match channel {
+            Channel::_1 => {
+                ccer.modify(|_, w| w.cc1e().clear_bit().cc1ne().clear_bit())
+            }
+            Channel::_2 => {
+                ccer.modify(|_, w| w.cc2e().clear_bit().cc2ne().clear_bit())
+            }
+            Channel::_3 => {
+                ccer.modify(|_, w| w.cc3e().clear_bit().cc3ne().clear_bit())
+            }
+            Channel::_4 => ccer.modify(|_, w| w.cc4e().clear_bit()),
+        }
+    }
+
+    fn enable(&self, channel: Channel) {
+        let ccer = &self.0.ccer;
+
+        match channel {
+            Channel::_1 => {
+                ccer.modify(|_, w| w.cc1e().set_bit().cc1ne().set_bit())
+            }
+            Channel::_2 => {
+                ccer.modify(|_, w| w.cc2e().set_bit().cc2ne().set_bit())
+            }
+            Channel::_3 => {
+                ccer.modify(|_, w| w.cc3e().set_bit().cc3ne().set_bit())
+            }
+            Channel::_4 => ccer.modify(|_, w| w.cc4e().set_bit()),
+        }
+    }
+
+    fn get_duty(&self, channel: Channel) -> u16 {
+        let tim1 = self.0;
+
+        let ccr = match channel {
+            Channel::_1 => tim1.ccr1.read().bits(),
+            Channel::_2 => tim1.ccr2.read().bits(),
+            Channel::_3 => tim1.ccr3.read().bits(),
+            Channel::_4 => tim1.ccr4.read().bits(),
+        };
+
+        u16(ccr).unwrap()
+    }
+
+    fn get_max_duty(&self) -> u16 {
+        u16(self.0.arr.read().bits()).unwrap()
+    }
+
+    fn get_period(&self) -> ::apb2::Ticks {
+        let tim1 = self.0;
+
+        let psc = u32(tim1.psc.read().psc().bits());
+        let arr = tim1.arr.read().bits();
+
+        ::apb2::Ticks((psc + 1) * arr)
+    }
+
+    fn set_duty(&self, channel: Channel, duty: u16) {
+        let tim1 = self.0;
+
+        match channel {
+            Channel::_1 => tim1.ccr1.write(|w| unsafe { w.bits(u32(duty)) }),
+            Channel::_2 => tim1.ccr2.write(|w| unsafe { w.bits(u32(duty)) }),
+            Channel::_3 => tim1.ccr3.write(|w| unsafe { w.bits(u32(duty)) }),
+            Channel::_4 => tim1.ccr4.write(|w| unsafe { w.bits(u32(duty)) }),
+        }
+    }
+
+    fn set_period<P>(&self, period: P)
+    where
+        P: Into<::apb2::Ticks>,
+    {
+        self._set_period(period.into()).unwrap()
+    }
+}
+
+/// Encodes a dead time of `ticks` timer clock cycles into the `DTG` field
+///
+/// The dead time is rounded down to the closest value the hardware can
+/// represent:
+///
+/// - `0xxx_xxxx`: `DTG[6:0]` (0 - 127)
+/// - `10xx_xxxx`: `(64 + DTG[5:0]) * 2` (128 - 254)
+/// - `110x_xxxx`: `(32 + DTG[4:0]) * 8` (256 - 504)
+/// - `111x_xxxx`: `(32 + DTG[4:0]) * 16` (512 - 1008)
+fn dtg(ticks: u32) -> u8 {
+    assert!(ticks <= 1008, "impossible dead time");
+
+    u8(if ticks < 128 {
+        ticks
+    } else if ticks < 256 {
+        0b1000_0000 | (ticks / 2 - 64)
+    } else if ticks < 512 {
+        0b1100_0000 | (ticks / 8 - 32)
+    } else {
+        0b1110_0000 | (ticks / 16 - 32)
+    }).unwrap()
+}
@@ -0,0 +1,82 @@
+//! Quadrature Encoder Interface
+//!
+//! You can use the `Qei` interface with these TIM instances
+//!
+//! # TIM4
+//!
+//! - CH1 = PD12
+//! - CH2 = PD13
+//!
+//! The timer counts the edges of both channels and the direction of the count
+//! follows the phase between them; no CPU intervention is required to decode
+//! the quadrature signal.
+
+use cast::u16;
+use hal;
+use stm32f30x::{GPIOD, RCC, TIM4};
+
+/// Quadrature Encoder Interface
+pub struct Qei<'a, T>(pub &'a T)
+where
+    T: 'a;
+
+impl<'a> Qei<'a, TIM4> {
+    /// Initializes the quadrature encoder interface
+    ///
+    /// The counter will count up and down on both edges of both channels and
+    /// starts at zero
+    pub fn init(&self, gpiod: &GPIOD, rcc: &RCC) {
+        let tim4 = self.0;
+
+        // power up peripherals
+        rcc.apb1enr.modify(|_, w| w.tim4en().enabled());
+        rcc.ahbenr.modify(|_, w| w.iopden().set_bit());
+
+        // PD12 = CH1, PD13 = CH2
+        gpiod.afrh.modify(|_, w| unsafe { w.afrh12().bits(2).afrh13().bits(2) });
+        gpiod
+            .moder
+            .modify(|_, w| w.moder12().alternate().moder13().alternate());
+
+        // CC1S: TI1FP1 mapped on TI1
+        // CC2S: TI2FP2 mapped on TI2
+        tim4.ccmr1_input
+            .write(|w| unsafe { w.cc1s().bits(0b01).cc2s().bits(0b01) });
+
+        // non inverted inputs
+        tim4.ccer.write(|w| {
+            w.cc1p()
+                .clear_bit()
+                .cc1np()
+                .clear_bit()
+                .cc2p()
+                .clear_bit()
+                .cc2np()
+                .clear_bit()
+        });
+
+        // SMS: encoder mode 3 (count on both TI1FP1 and TI2FP2 edges)
+        tim4.smcr.write(|w| unsafe { w.sms().bits(0b011) });
+
+        tim4.arr.write(|w| unsafe { w.bits(0xffff) });
+        tim4.cnt.write(|w| unsafe { w.bits(0) });
+
+        tim4.cr1.write(|w| w.cen().set_bit());
+    }
+}
+
+impl<'a> hal::Qei for Qei<'a, TIM4> {
+    type Count = u16;
+
+    fn count(&self) -> u16 {
+        u16(self.0.cnt.read().bits()).unwrap()
+    }
+
+    fn direction(&self) -> hal::Direction {
+        if self.0.cr1.read().dir().bit_is_clear() {
+            hal::Direction::Upcounting
+        } else {
+            hal::Direction::Downcounting
+        }
+    }
+}
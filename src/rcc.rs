@@ -0,0 +1,230 @@
+//! Reset and Clock Control
+//!
+//! # Peripheral reset
+//!
+//! A peripheral that got stuck in a bad state (e.g. an I2C peripheral that
+//! thinks the bus is busy) can be brought back to its reset state by pulsing
+//! its bit in one of the RCC reset registers (`AHBRSTR`, `APB1RSTR` or
+//! `APB2RSTR`). All its registers go back to their reset values so the
+//! peripheral must be initialized again afterwards.
+//!
+//! # Clock gating
+//!
+//! Unused peripherals can have their clock gated off to reduce the current
+//! consumption. Disable the peripheral through its own enable bit (e.g.
+//! `UE`, `SPE` or `PE`) and wait until it's idle *before* gating its clock;
+//! a peripheral whose clock is stopped mid-operation stays frozen in that
+//! state. While its clock is off the registers of the peripheral can't be
+//! written.
+//!
+//! # Reset cause
+//!
+//! The reset flags in the `CSR` register record what caused the last reset.
+//! They survive system resets, so read them with `reset_cause` early after
+//! boot and then `clear_reset_flags` to have the next reset reported
+//! correctly.
+
+use stm32f30x::RCC;
+
+/// A peripheral that the RCC can reset and clock gate
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Peripheral {
+    /// ADC1 and ADC2
+    Adc12,
+    /// GPIOA
+    Gpioa,
+    /// GPIOB
+    Gpiob,
+    /// GPIOC
+    Gpioc,
+    /// GPIOD
+    Gpiod,
+    /// GPIOE
+    Gpioe,
+    /// GPIOF
+    Gpiof,
+    /// I2C1
+    I2c1,
+    /// I2C2
+    I2c2,
+    /// SPI1
+    Spi1,
+    /// SPI2
+    Spi2,
+    /// SPI3
+    Spi3,
+    /// SYSCFG
+    Syscfg,
+    /// TIM1
+    Tim1,
+    /// TIM2
+    Tim2,
+    /// TIM3
+    Tim3,
+    /// TIM4
+    Tim4,
+    /// TIM6
+    Tim6,
+    /// TIM7
+    Tim7,
+    /// USART1
+    Usart1,
+    /// USART2
+    Usart2,
+    /// USART3
+    Usart3,
+}
+
+/// Bus a peripheral is connected to
+enum Bus {
+    Ahb,
+    Apb1,
+    Apb2,
+}
+
+impl Peripheral {
+    /// Returns the bus of the peripheral and the position of its bit in the
+    /// reset (and clock enable) registers of that bus
+    fn bit(self) -> (Bus, u32) {
+        match self {
+            Peripheral::Adc12 => (Bus::Ahb, 28),
+            Peripheral::Gpioa => (Bus::Ahb, 17),
+            Peripheral::Gpiob => (Bus::Ahb, 18),
+            Peripheral::Gpioc => (Bus::Ahb, 19),
+            Peripheral::Gpiod => (Bus::Ahb, 20),
+            Peripheral::Gpioe => (Bus::Ahb, 21),
+            Peripheral::Gpiof => (Bus::Ahb, 22),
+            Peripheral::I2c1 => (Bus::Apb1, 21),
+            Peripheral::I2c2 => (Bus::Apb1, 22),
+            Peripheral::Spi1 => (Bus::Apb2, 12),
+            Peripheral::Spi2 => (Bus::Apb1, 14),
+            Peripheral::Spi3 => (Bus::Apb1, 15),
+            Peripheral::Syscfg => (Bus::Apb2, 0),
+            Peripheral::Tim1 => (Bus::Apb2, 11),
+            Peripheral::Tim2 => (Bus::Apb1, 0),
+            Peripheral::Tim3 => (Bus::Apb1, 1),
+            Peripheral::Tim4 => (Bus::Apb1, 2),
+            Peripheral::Tim6 => (Bus::Apb1, 4),
+            Peripheral::Tim7 => (Bus::Apb1, 5),
+            Peripheral::Usart1 => (Bus::Apb2, 14),
+            Peripheral::Usart2 => (Bus::Apb1, 17),
+            Peripheral::Usart3 => (Bus::Apb1, 18),
+        }
+    }
+}
+
+/// Cause of the last reset
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResetCause {
+    /// Entering Standby or Stop mode while that's disallowed by the option
+    /// bytes
+    LowPower,
+    /// Window watchdog
+    WindowWatchdog,
+    /// Independent watchdog
+    IndependentWatchdog,
+    /// Software reset (`SYSRESETREQ`)
+    Software,
+    /// Power on or brown-out
+    PowerOn,
+    /// Option byte loading
+    OptionByteLoader,
+    /// The NRST pin was pulled low
+    Pin,
+    /// No reset flag is set, e.g. the flags were already cleared
+    Unknown,
+}
+
+/// Returns the cause of the last reset
+///
+/// NOTE the internal resets also drive the NRST pin low so the `Pin` flag can
+/// be set along with the flag of the actual cause; the latter takes
+/// precedence
+pub fn reset_cause(rcc: &RCC) -> ResetCause {
+    let csr = rcc.csr.read().bits();
+
+    // (flag, cause) in decreasing order of precedence
+    let flags = [
+        (31, ResetCause::LowPower),
+        (30, ResetCause::WindowWatchdog),
+        (29, ResetCause::IndependentWatchdog),
+        (28, ResetCause::Software),
+        (27, ResetCause::PowerOn),
+        (25, ResetCause::OptionByteLoader),
+        (26, ResetCause::Pin),
+    ];
+
+    flags
+        .iter()
+        .find(|&&(i, _)| csr & (1 << i) != 0)
+        .map(|&(_, cause)| cause)
+        .unwrap_or(ResetCause::Unknown)
+}
+
+/// Clears the reset flags by setting the `RMVF` bit
+pub fn clear_reset_flags(rcc: &RCC) {
+    rcc.csr.modify(|r, w| unsafe { w.bits(r.bits() | (1 << 24)) });
+}
+
+/// Resets the `peripheral`
+///
+/// NOTE the peripheral needs to be initialized again after this
+pub fn reset(peripheral: Peripheral, rcc: &RCC) {
+    let (bus, i) = peripheral.bit();
+
+    match bus {
+        Bus::Ahb => {
+            rcc.ahbrstr.modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) });
+            rcc.ahbrstr.modify(|r, w| unsafe { w.bits(r.bits() & !(1 << i)) });
+        }
+        Bus::Apb1 => {
+            rcc.apb1rstr.modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) });
+            rcc.apb1rstr
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << i)) });
+        }
+        Bus::Apb2 => {
+            rcc.apb2rstr.modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) });
+            rcc.apb2rstr
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << i)) });
+        }
+    }
+}
+
+/// Enables the clock of the `peripheral`
+pub fn enable_clock(peripheral: Peripheral, rcc: &RCC) {
+    let (bus, i) = peripheral.bit();
+
+    match bus {
+        Bus::Ahb => {
+            rcc.ahbenr.modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) })
+        }
+        Bus::Apb1 => {
+            rcc.apb1enr.modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) })
+        }
+        Bus::Apb2 => {
+            rcc.apb2enr.modify(|r, w| unsafe { w.bits(r.bits() | (1 << i)) })
+        }
+    }
+}
+
+/// Disables the clock of the `peripheral`
+///
+/// NOTE disable the peripheral itself before calling this; see the module
+/// documentation
+pub fn disable_clock(peripheral: Peripheral, rcc: &RCC) {
+    let (bus, i) = peripheral.bit();
+
+    match bus {
+        Bus::Ahb => {
+            rcc.ahbenr.modify(|r, w| unsafe { w.bits(r.bits() & !(1 << i)) })
+        }
+        Bus::Apb1 => {
+            rcc.apb1enr
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << i)) })
+        }
+        Bus::Apb2 => {
+            rcc.apb2enr
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << i)) })
+        }
+    }
+}
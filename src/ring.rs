@@ -0,0 +1,128 @@
+//! Lock-free ring buffer
+//!
+//! A `RingBuffer` can be shared between one producer and one consumer that
+//! run in different execution contexts, e.g. an interrupt handler that
+//! `push`es received data and the main loop that `pop`s it, without a
+//! critical section. Each index is only written by one side and the atomic
+//! accesses order the accesses to the items.
+//!
+//! NOTE having more than one producer, or more than one consumer, is a data
+//! race; it's up to the user to avoid that.
+
+use core::cell::UnsafeCell;
+use core::marker::{PhantomData, Unsize};
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Fixed capacity, single producer single consumer, FIFO of `T` items
+/// stored in the array `B`
+///
+/// The capacity is one less than the length of `B`; one slot is always left
+/// empty to tell a full buffer from an empty one.
+///
+/// # Panics
+///
+/// All the methods that access the items panic if `B` is empty
+pub struct RingBuffer<T, B> {
+    _marker: PhantomData<T>,
+    buffer: UnsafeCell<B>,
+    // index of the next item to pop; only written by the consumer
+    head: AtomicUsize,
+    // index of the next free slot; only written by the producer
+    tail: AtomicUsize,
+}
+
+unsafe impl<T, B> Sync for RingBuffer<T, B>
+where
+    T: Send,
+{
+}
+
+impl<T, B> RingBuffer<T, B> {
+    /// Creates an empty ring buffer backed by `buffer`
+    pub const fn new(buffer: B) -> Self {
+        RingBuffer {
+            _marker: PhantomData,
+            buffer: UnsafeCell::new(buffer),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<T, B> RingBuffer<T, B>
+where
+    T: Copy,
+    B: Unsize<[T]>,
+{
+    /// Returns the maximum number of items the buffer can hold
+    pub fn capacity(&self) -> usize {
+        self.slots() - 1
+    }
+
+    /// Returns the number of items in the buffer
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Relaxed);
+
+        (tail + self.slots() - head) % self.slots()
+    }
+
+    /// Returns `true` if the buffer holds no item
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if no more items can be pushed into the buffer
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    /// Takes the oldest item out of the buffer
+    ///
+    /// NOTE only the consumer may call this method
+    pub fn pop(&self) -> Option<T> {
+        let head = self.head.load(Ordering::Relaxed);
+
+        if head == self.tail.load(Ordering::Acquire) {
+            None
+        } else {
+            let buffer: *mut [T] = self.buffer.get();
+            let item =
+                unsafe { ptr::read((buffer as *mut T).offset(head as isize)) };
+
+            self.head.store((head + 1) % self.slots(), Ordering::Release);
+
+            Some(item)
+        }
+    }
+
+    /// Adds `item` to the buffer
+    ///
+    /// Returns the `item` back if the buffer is full
+    ///
+    /// NOTE only the producer may call this method
+    pub fn push(&self, item: T) -> Result<(), T> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let next = (tail + 1) % self.slots();
+
+        if next == self.head.load(Ordering::Acquire) {
+            Err(item)
+        } else {
+            let buffer: *mut [T] = self.buffer.get();
+            unsafe {
+                ptr::write((buffer as *mut T).offset(tail as isize), item);
+            }
+
+            self.tail.store(next, Ordering::Release);
+
+            Ok(())
+        }
+    }
+
+    fn slots(&self) -> usize {
+        let buffer: &[T] = unsafe { &*self.buffer.get() };
+        assert_ne!(buffer.len(), 0, "empty ring buffer");
+        buffer.len()
+    }
+}
@@ -0,0 +1,201 @@
+//! Real Time Clock
+//!
+//! The RTC keeps a calendar (time and date) that ticks once per second. The
+//! RTC and its clock live in the backup domain so the calendar survives
+//! system resets.
+//!
+//! # Write protection
+//!
+//! The RTC registers are write protected. To modify them the key `0xCA`
+//! followed by the key `0x53` must be written to the `WPR` register; writing
+//! any other value restores the protection. This module takes care of that
+//! sequence.
+
+use cast::{u8, u32};
+use stm32f30x::{PWR, RCC, RTC};
+
+/// Clock source of the RTC
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClockSource {
+    /// Low Speed External oscillator (32.768 kHz crystal)
+    Lse,
+    /// Low Speed Internal oscillator (~40 kHz RC oscillator)
+    ///
+    /// NOTE this oscillator is not accurate; expect the calendar to drift
+    Lsi,
+}
+
+/// Time of the day (24-hour format)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Time {
+    /// Hours (0-23)
+    pub hours: u8,
+    /// Minutes (0-59)
+    pub minutes: u8,
+    /// Seconds (0-59)
+    pub seconds: u8,
+}
+
+/// Calendar date
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Date {
+    /// Year (0-99), counting from 2000
+    pub year: u8,
+    /// Month (1-12)
+    pub month: u8,
+    /// Day of the month (1-31)
+    pub day: u8,
+    /// Day of the week (1 = Monday, 7 = Sunday)
+    pub weekday: u8,
+}
+
+/// Real Time Clock
+pub struct Rtc<'a>(pub &'a RTC);
+
+impl<'a> Rtc<'a> {
+    /// Initializes the RTC to tick at 1 Hz using the clock `source`
+    ///
+    /// If the RTC was already running (e.g. it was initialized before the last
+    /// system reset) it is left untouched so the calendar is preserved. NOTE
+    /// the clock source of a running RTC can only be changed after a backup
+    /// domain reset.
+    pub fn init(&self, source: ClockSource, pwr: &PWR, rcc: &RCC) {
+        let rtc = self.0;
+
+        // power up peripherals
+        rcc.apb1enr.modify(|_, w| w.pwren().enabled());
+
+        // unlock the backup domain
+        pwr.cr.modify(|_, w| w.dbp().set_bit());
+
+        if rcc.bdcr.read().rtcen().bit_is_set() &&
+            rtc.isr.read().inits().bit_is_set()
+        {
+            return;
+        }
+
+        // start the oscillator and pick the (asynchronous, synchronous)
+        // prescaler values that yield a 1 Hz calendar
+        let (rtcsel, prediv_a, prediv_s) = match source {
+            ClockSource::Lse => {
+                rcc.bdcr.modify(|_, w| w.lseon().set_bit());
+                while rcc.bdcr.read().lserdy().bit_is_clear() {}
+
+                // 32_768 Hz / 128 / 256 = 1 Hz
+                (0b01, 127, 255)
+            }
+            ClockSource::Lsi => {
+                rcc.csr.modify(|_, w| w.lsion().set_bit());
+                while rcc.csr.read().lsirdy().bit_is_clear() {}
+
+                // 40_000 Hz / 100 / 400 = 1 Hz
+                (0b10, 99, 399)
+            }
+        };
+
+        rcc.bdcr.modify(|_, w| unsafe {
+            w.rtcsel().bits(rtcsel).rtcen().set_bit()
+        });
+
+        self.modify(|| {
+            // the two prescaler values must be written in two separate
+            // accesses
+            rtc.prer.write(|w| unsafe { w.prediv_s().bits(prediv_s) });
+            rtc.prer.modify(|_, w| unsafe { w.prediv_a().bits(prediv_a) });
+
+            // 24-hour format
+            rtc.cr.modify(|_, w| w.fmt().clear_bit());
+        });
+    }
+
+    /// Returns the current time
+    pub fn get_time(&self) -> Time {
+        let rtc = self.0;
+
+        let tr = rtc.tr.read().bits();
+        // NOTE reading TR freezes the DR shadow register until DR is read
+        rtc.dr.read();
+
+        Time {
+            hours: bcd2dec(tr >> 16, 0b11),
+            minutes: bcd2dec(tr >> 8, 0b111),
+            seconds: bcd2dec(tr, 0b111),
+        }
+    }
+
+    /// Changes the current time
+    pub fn set_time(&self, time: &Time) {
+        assert!(time.hours < 24 && time.minutes < 60 && time.seconds < 60);
+
+        let rtc = self.0;
+
+        let tr = dec2bcd(time.hours) << 16 | dec2bcd(time.minutes) << 8 |
+            dec2bcd(time.seconds);
+
+        self.modify(|| rtc.tr.write(|w| unsafe { w.bits(tr) }));
+    }
+
+    /// Returns the current date
+    pub fn get_date(&self) -> Date {
+        let dr = self.0.dr.read().bits();
+
+        Date {
+            year: bcd2dec(dr >> 16, 0b1111),
+            month: bcd2dec(dr >> 8, 0b1),
+            day: bcd2dec(dr, 0b11),
+            weekday: u8((dr >> 13) & 0b111).unwrap(),
+        }
+    }
+
+    /// Changes the current date
+    pub fn set_date(&self, date: &Date) {
+        assert!(date.year < 100);
+        assert!(date.month >= 1 && date.month <= 12);
+        assert!(date.day >= 1 && date.day <= 31);
+        assert!(date.weekday >= 1 && date.weekday <= 7);
+
+        let rtc = self.0;
+
+        let dr = dec2bcd(date.year) << 16 | u32(date.weekday) << 13 |
+            dec2bcd(date.month) << 8 | dec2bcd(date.day);
+
+        self.modify(|| rtc.dr.write(|w| unsafe { w.bits(dr) }));
+    }
+
+    /// Runs `f` with the write protection disabled and the RTC in
+    /// initialization mode
+    fn modify<F>(&self, f: F)
+    where
+        F: FnOnce(),
+    {
+        let rtc = self.0;
+
+        // disable the write protection
+        rtc.wpr.write(|w| unsafe { w.key().bits(0xCA) });
+        rtc.wpr.write(|w| unsafe { w.key().bits(0x53) });
+
+        // enter initialization mode; this stops the calendar
+        rtc.isr.modify(|_, w| w.init().set_bit());
+        while rtc.isr.read().initf().bit_is_clear() {}
+
+        f();
+
+        // exit initialization mode and wait until the shadow registers are
+        // synchronized with the new calendar values
+        rtc.isr.modify(|_, w| w.init().clear_bit().rsf().clear_bit());
+        while rtc.isr.read().rsf().bit_is_clear() {}
+
+        // restore the write protection
+        rtc.wpr.write(|w| unsafe { w.key().bits(0xFF) });
+    }
+}
+
+/// Converts a BCD field to decimal. `tens` is the mask of the tens digit
+fn bcd2dec(bcd: u32, tens: u32) -> u8 {
+    u8(((bcd >> 4) & tens) * 10 + (bcd & 0b1111)).unwrap()
+}
+
+/// Converts a decimal number (0-99) to BCD
+fn dec2bcd(dec: u8) -> u32 {
+    u32(dec / 10) << 4 | u32(dec % 10)
+}
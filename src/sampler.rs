@@ -0,0 +1,123 @@
+//! Periodic sampling of sensors
+
+use core::marker::Unsize;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use cast::usize;
+use nb;
+
+use ring::RingBuffer;
+
+/// A sensor that takes measurements
+pub trait Sensor {
+    /// A measurement
+    type Output: Copy;
+    /// Measurement error
+    type Error;
+
+    /// Takes a measurement
+    ///
+    /// Sensors that need some time to complete a measurement return
+    /// `WouldBlock` until the measurement is available
+    fn measure(&self) -> nb::Result<Self::Output, Self::Error>;
+}
+
+/// Takes a measurement from a `Sensor` every `period` ticks and keeps them
+/// in a buffer until they are `pop`ped
+///
+/// Call `tick` from a periodic task (e.g. a timer interrupt). The
+/// measurements are stored in a `RingBuffer` where `tick` is the producer and
+/// `pop` the consumer; measurements taken while the buffer is full are
+/// discarded.
+///
+/// The sampler can be shared between the two execution contexts without a
+/// critical section, as long as the sensor is `Sync`. NOTE `tick` must
+/// always be called from the same execution context, and so must `pop`
+pub struct Sampler<S, B>
+where
+    S: Sensor,
+{
+    sensor: S,
+    period: u32,
+    // NOTE these are only accessed by `tick`; they are atomics so that the
+    // sampler can be shared with the consumer
+    elapsed: AtomicUsize,
+    measuring: AtomicBool,
+    samples: RingBuffer<S::Output, B>,
+}
+
+impl<S, B> Sampler<S, B>
+where
+    S: Sensor,
+    B: Unsize<[S::Output]>,
+{
+    /// Creates a sampler that measures `sensor` every `period` ticks and
+    /// stores the measurements in `buffer`
+    ///
+    /// The sampler can hold one measurement less than the length of `buffer`
+    ///
+    /// # Panics
+    ///
+    /// If `period` is zero or `buffer` has less than two elements
+    pub fn new(sensor: S, period: u32, buffer: B) -> Self {
+        assert_ne!(period, 0);
+
+        let sampler = Sampler {
+            sensor: sensor,
+            period: period,
+            elapsed: AtomicUsize::new(0),
+            measuring: AtomicBool::new(false),
+            samples: RingBuffer::new(buffer),
+        };
+
+        assert_ne!(sampler.samples.capacity(), 0);
+
+        sampler
+    }
+
+    /// Advances the schedule by one tick
+    ///
+    /// Starts a new measurement every `period` ticks and polls the
+    /// measurement in progress. Returns `true` if a measurement was stored.
+    /// A failed measurement is discarded and its error returned.
+    pub fn tick(&self) -> Result<bool, S::Error> {
+        let elapsed = self.elapsed.load(Ordering::Relaxed) + 1;
+        if elapsed == usize(self.period) {
+            self.elapsed.store(0, Ordering::Relaxed);
+            self.measuring.store(true, Ordering::Relaxed);
+        } else {
+            self.elapsed.store(elapsed, Ordering::Relaxed);
+        }
+
+        if !self.measuring.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+
+        match self.sensor.measure() {
+            Ok(output) => {
+                self.measuring.store(false, Ordering::Relaxed);
+                Ok(self.samples.push(output).is_ok())
+            }
+            Err(nb::Error::Other(e)) => {
+                self.measuring.store(false, Ordering::Relaxed);
+                Err(e)
+            }
+            Err(nb::Error::WouldBlock) => Ok(false),
+        }
+    }
+
+    /// Takes the oldest stored measurement out of the buffer
+    pub fn pop(&self) -> Option<S::Output> {
+        self.samples.pop()
+    }
+
+    /// Returns the number of stored measurements
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns `true` if there are no stored measurements
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
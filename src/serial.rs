@@ -9,18 +9,20 @@
 //! - Interrupt = USART1
 
 use core::any::{Any, TypeId};
+use core::fmt;
 use core::marker::Unsize;
 use core::ops::Deref;
 use core::ptr;
 
-use cast::u16;
+use cast::{u16, u32, usize};
 use hal;
 use nb;
 use static_ref::Static;
 use stm32f30x::{gpioa, DMA1, USART1, usart1, GPIOA,
                   RCC};
 
-use dma::{self, Buffer, Dma1Channel4, Dma1Channel5};
+use dma::{self, Buffer, Dma1Channel4, Dma1Channel5, Ref};
+use ring::RingBuffer;
 
 /// Specialized `Result` type
 pub type Result<T> = ::core::result::Result<T, nb::Error<Error>>;
@@ -40,6 +42,7 @@ unsafe impl Usart for USART1 {
 
 /// An error
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// De-synchronization, excessive noise or a break character detected
     Framing,
@@ -47,8 +50,128 @@ pub enum Error {
     Noise,
     /// RX buffer overrun
     Overrun,
-    #[doc(hidden)]
-    _Extensible,
+    /// Parity check error
+    Parity,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Error::Framing => "serial framing error",
+            Error::Noise => "serial noise error",
+            Error::Overrun => "serial overrun",
+            Error::Parity => "serial parity error",
+        })
+    }
+}
+
+/// Error returned by `read_exact_blocking`
+#[derive(Debug)]
+pub struct ReadExactError {
+    /// Number of bytes that were read before the error
+    pub read: usize,
+    /// The error
+    pub error: Error,
+}
+
+impl fmt::Display for ReadExactError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} after reading {} bytes", self.error, self.read)
+    }
+}
+
+/// Error returned by `self_test`
+#[derive(Debug)]
+pub enum SelfTestError {
+    /// A byte other than the one sent was received
+    Mismatch(u8),
+    /// The transfer failed
+    Serial(Error),
+}
+
+impl fmt::Display for SelfTestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SelfTestError::Mismatch(byte) => {
+                write!(f, "serial self test received {:#04x}", byte)
+            }
+            SelfTestError::Serial(ref e) => e.fmt(f),
+        }
+    }
+}
+
+/// Number of data bits in a frame
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DataBits {
+    /// 8 data bits
+    Eight,
+    /// 9 data bits
+    Nine,
+}
+
+/// Parity checking
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Parity {
+    /// No parity bit
+    None,
+    /// Even parity
+    Even,
+    /// Odd parity
+    Odd,
+}
+
+/// Number of stop bits
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StopBits {
+    /// 1 stop bit
+    One,
+    /// 2 stop bits
+    Two,
+}
+
+/// Receiver oversampling
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Oversampling {
+    /// Each bit is sampled 16 times
+    By16,
+    /// Each bit is sampled 8 times
+    ///
+    /// This doubles the maximum baud rate (`f_CK / 8`) and gives finer baud
+    /// rates at the high end, at the cost of a lower tolerance to clock
+    /// deviation and noise on the RX line
+    By8,
+}
+
+/// Configuration of the serial interface
+///
+/// The default configuration is 8N1 (8 data bits, no parity and 1 stop bit)
+/// with oversampling by 16
+///
+/// NOTE the parity bit takes the place of the most significant bit of the
+/// hardware word, so 8 data bits plus parity uses 9 bit words (`CR1.M = 1`).
+/// The word length is chosen accordingly; 9 data bits plus parity is not
+/// supported
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    /// Number of data bits
+    pub data_bits: DataBits,
+    /// Parity checking
+    pub parity: Parity,
+    /// Number of stop bits
+    pub stop_bits: StopBits,
+    /// Receiver oversampling
+    pub oversampling: Oversampling,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            oversampling: Oversampling::By16,
+        }
+    }
 }
 
 /// Interrupt event
@@ -59,6 +182,9 @@ pub enum Event {
     Tc,
     /// TX buffer Empty (more data can be send)
     Txe,
+    /// IDLE line detected (the RX line has been idle for one frame after
+    /// receiving data)
+    Idle,
 }
 
 /// Serial interface
@@ -103,12 +229,32 @@ where
     ) where
         B: Into<U::Ticks>,
     {
-        self._init(baud_rate.into(), dma1, gpio, rcc)
+        self._init(baud_rate.into(), Config::default(), dma1, gpio, rcc)
+    }
+
+    /// Initializes the serial interface with a baud rate of `baud_rate` bits
+    /// per second and the frame format specified in `config`
+    ///
+    /// # Panics
+    ///
+    /// If `config` asks for 9 data bits plus a parity bit
+    pub fn init_with<B>(
+        &self,
+        baud_rate: B,
+        config: Config,
+        dma1: Option<&DMA1>,
+        gpio: &U::GPIO,
+        rcc: &RCC,
+    ) where
+        B: Into<U::Ticks>,
+    {
+        self._init(baud_rate.into(), config, dma1, gpio, rcc)
     }
 
     fn _init(
         &self,
         baud_rate: U::Ticks,
+        config: Config,
         dma1: Option<&DMA1>,
         gpio: &U::GPIO,
         rcc: &RCC,
@@ -212,12 +358,37 @@ where
             }
         }
 
-        // 8N1
-        usart.cr2.write(|w| unsafe { w.stop().bits(0b00) });
+        // the parity bit is part of the hardware word
+        let m = match (config.data_bits, config.parity) {
+            (DataBits::Eight, Parity::None) => false,
+            (DataBits::Eight, _) | (DataBits::Nine, Parity::None) => true,
+            (DataBits::Nine, _) => panic!("9 data bits plus parity"),
+        };
+        let (pce, ps) = match config.parity {
+            Parity::None => (false, false),
+            Parity::Even => (true, false),
+            Parity::Odd => (true, true),
+        };
+        let stop = match config.stop_bits {
+            StopBits::One => 0b00,
+            StopBits::Two => 0b10,
+        };
+
+        usart.cr2.write(|w| unsafe { w.stop().bits(stop) });
 
         // baud rate
-        let brr = baud_rate.into();
-        assert!(brr >= 16, "impossible baud rate");
+        let over8 = config.oversampling == Oversampling::By8;
+        let brr = if over8 {
+            // USARTDIV = 2 * f_CK / baud_rate; BRR[2:0] = USARTDIV[3:0] >> 1
+            // and BRR[3] must be kept cleared
+            let div = 2 * baud_rate.into();
+            assert!(div >= 16, "impossible baud rate");
+            (div & !0b1111) | ((div & 0b1111) >> 1)
+        } else {
+            let div = baud_rate.into();
+            assert!(div >= 16, "impossible baud rate");
+            div
+        };
         usart.brr.write(|w| unsafe { w.bits(brr) });
 
         // disable hardware flow control
@@ -233,7 +404,7 @@ where
                 .set_bit()
         });
 
-        // enable TX, RX; disable parity checking
+        // enable TX, RX; set the word length and parity checking
         usart.cr1.write(|w| {
             w.ue()
                 .set_bit()
@@ -242,11 +413,13 @@ where
                 .te()
                 .set_bit()
                 .m()
-                .clear_bit()
+                .bit(m)
                 .over8()
-                .clear_bit()
+                .bit(over8)
                 .pce()
-                .clear_bit()
+                .bit(pce)
+                .ps()
+                .bit(ps)
                 .rxneie()
                 .clear_bit()
         });
@@ -260,6 +433,7 @@ where
             Event::Rxne => usart.cr1.modify(|_, w| w.rxneie().set_bit()),
             Event::Tc => usart.cr1.modify(|_, w| w.tcie().set_bit()),
             Event::Txe => usart.cr1.modify(|_, w| w.txeie().set_bit()),
+            Event::Idle => usart.cr1.modify(|_, w| w.idleie().set_bit()),
         }
     }
 
@@ -271,6 +445,144 @@ where
             Event::Rxne => usart.cr1.modify(|_, w| w.rxneie().clear_bit()),
             Event::Tc => usart.cr1.modify(|_, w| w.tcie().clear_bit()),
             Event::Txe => usart.cr1.modify(|_, w| w.txeie().clear_bit()),
+            Event::Idle => usart.cr1.modify(|_, w| w.idleie().clear_bit()),
+        }
+    }
+
+    /// Clears the IDLE line flag
+    ///
+    /// Returns `true` if the flag was set. Call this from the handler of the
+    /// `Idle` event or the interrupt will fire again as soon as the handler
+    /// returns
+    ///
+    /// NOTE on this device the flag is cleared by writing to the `ICR`
+    /// register. The "read SR then read DR" sequence used on other STM32
+    /// families does *not* clear it here, and reading DR would also consume
+    /// a received byte
+    pub fn clear_idle_flag(&self) -> bool {
+        let usart = self.0;
+
+        if usart.isr.read().idle().bit_is_set() {
+            usart.icr.write(|w| w.idlecf().set_bit());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Blocks until `buffer` has been filled with received bytes
+    ///
+    /// Stops at the first error; the returned error reports how many bytes
+    /// were stored in `buffer` before the error occurred
+    pub fn read_exact_blocking(
+        &self,
+        buffer: &mut [u8],
+    ) -> ::core::result::Result<(), ReadExactError> {
+        for (i, slot) in buffer.iter_mut().enumerate() {
+            *slot = block!(hal::serial::Read::read(self)).map_err(|error| {
+                ReadExactError {
+                    read: i,
+                    error: error,
+                }
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends `byte` and checks that it's received back
+    ///
+    /// The USART is temporarily put in half-duplex mode, where the TX line is
+    /// internally connected to the receiver, so no external wiring is needed.
+    /// This checks the frame format and the enabled state of the transmitter
+    /// and the receiver. As both sides share the same clock it does *not*
+    /// catch a baud rate that doesn't match the one of the remote end.
+    ///
+    /// NOTE the byte is sent out through the TX pin and data arriving on the
+    /// RX pin is ignored during the test
+    pub fn self_test(
+        &self,
+        byte: u8,
+    ) -> ::core::result::Result<(), SelfTestError> {
+        let usart = self.0;
+
+        // HDSEL can only be changed while the USART is disabled
+        usart.cr1.modify(|_, w| w.ue().clear_bit());
+        usart.cr3.modify(|_, w| w.hdsel().set_bit());
+        usart.cr1.modify(|_, w| w.ue().set_bit());
+
+        // discard any stale data and error flag
+        usart.rqr.write(|w| w.rxfrq().set_bit());
+        usart.icr.write(|w| {
+            w.orecf()
+                .set_bit()
+                .ncf()
+                .set_bit()
+                .fecf()
+                .set_bit()
+                .pecf()
+                .set_bit()
+        });
+
+        let ret = block!(hal::serial::Write::write(self, byte))
+            .and_then(|_| block!(hal::serial::Read::read(self)))
+            .map_err(SelfTestError::Serial)
+            .and_then(|received| if received == byte {
+                Ok(())
+            } else {
+                Err(SelfTestError::Mismatch(received))
+            });
+
+        usart.cr1.modify(|_, w| w.ue().clear_bit());
+        usart.cr3.modify(|_, w| w.hdsel().clear_bit());
+        usart.cr1.modify(|_, w| w.ue().set_bit());
+
+        ret
+    }
+
+    /// Reads a whole 9-bit word
+    ///
+    /// Use this when the interface was initialized with `DataBits::Nine` and
+    /// no parity, e.g. for multi-drop protocols where the 9th bit flags an
+    /// address frame. `read` only returns the lower 8 bits
+    pub fn read9(&self) -> Result<u16> {
+        let usart = self.0;
+        let sr = usart.isr.read();
+
+        if sr.ore().bit_is_set() {
+            Err(nb::Error::Other(Error::Overrun))
+        } else if sr.nf().bit_is_set() {
+            Err(nb::Error::Other(Error::Noise))
+        } else if sr.fe().bit_is_set() {
+            Err(nb::Error::Other(Error::Framing))
+        } else if sr.pe().bit_is_set() {
+            Err(nb::Error::Other(Error::Parity))
+        } else if sr.rxne().bit_is_set() {
+            Ok(u16(usart.rdr.read().bits() & 0x1ff).unwrap())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Sends a whole 9-bit `word`
+    ///
+    /// The counterpart of `read9`; the bits of `word` above the 9th one are
+    /// ignored
+    pub fn write9(&self, word: u16) -> Result<()> {
+        let usart = self.0;
+        let sr = usart.isr.read();
+
+        if sr.ore().bit_is_set() {
+            Err(nb::Error::Other(Error::Overrun))
+        } else if sr.nf().bit_is_set() {
+            Err(nb::Error::Other(Error::Noise))
+        } else if sr.fe().bit_is_set() {
+            Err(nb::Error::Other(Error::Framing))
+        } else if sr.txe().bit_is_set() {
+            usart.tdr.write(|w| unsafe { w.bits(u32(word & 0x1ff)) });
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
         }
     }
 }
@@ -291,6 +603,8 @@ where
             Err(nb::Error::Other(Error::Noise))
         } else if sr.fe().bit_is_set() {
             Err(nb::Error::Other(Error::Framing))
+        } else if sr.pe().bit_is_set() {
+            Err(nb::Error::Other(Error::Parity))
         } else if sr.rxne().bit_is_set() {
             // NOTE(read_volatile) the register is 9 bits big but we'll only
             // work with the first 8 bits
@@ -346,23 +660,7 @@ impl<'a> Serial<'a, USART1> {
     where
         B: Unsize<[u8]>,
     {
-        let usart1 = self.0;
-
-        if dma1.ccr5.read().en().bit_is_set() {
-            return Err(dma::Error::InUse);
-        }
-
-        let buffer: &mut [u8] = buffer.lock_mut();
-
-        dma1.cndtr5
-            .write(|w| unsafe { w.ndt().bits(u16(buffer.len()).unwrap()) });
-        dma1.cpar5
-            .write(|w| unsafe { w.bits(&usart1.rdr as *const _ as u32) });
-        dma1.cmar5
-            .write(|w| unsafe { w.bits(buffer.as_ptr() as u32) });
-        dma1.ccr5.modify(|_, w| w.en().set_bit());
-
-        Ok(())
+        buffer.start_read_from::<u8, _>(dma1, self.0)
     }
 
     /// Starts a DMA transfer to send `buffer` through this serial port
@@ -377,22 +675,194 @@ impl<'a> Serial<'a, USART1> {
     where
         B: Unsize<[u8]>,
     {
-        let usart1 = self.0;
+        buffer.start_write_to::<u8, _>(dma1, self.0)
+    }
+
+    /// Starts a DMA transfer to receive a variable length frame into `buffer`
+    ///
+    /// The end of the frame is detected when the RX line goes idle. This
+    /// enables the IDLE interrupt; call `release_on_idle` from that interrupt
+    /// handler (or poll it) to get the received bytes
+    pub fn read_until_idle<B>(
+        &self,
+        dma1: &DMA1,
+        buffer: &Static<Buffer<B, Dma1Channel5>>,
+    ) -> ::core::result::Result<(), dma::Error>
+    where
+        B: Unsize<[u8]>,
+    {
+        // discard idle line events that precede this frame
+        self.clear_idle_flag();
+
+        self.read_exact(dma1, buffer)?;
+        self.listen(Event::Idle);
+
+        Ok(())
+    }
 
-        if dma1.ccr4.read().en().bit_is_set() {
-            return Err(dma::Error::InUse);
+    /// Completes a DMA transfer started with `read_until_idle`
+    ///
+    /// The transfer completes when the RX line goes idle or when the `buffer`
+    /// gets completely filled, whatever happens first. On completion this
+    /// stops the DMA transfer, releases the `buffer` and returns exactly the
+    /// bytes that were received
+    ///
+    /// If the transfer failed the `buffer` is released as well and
+    /// `Error::Transfer` is returned
+    pub fn release_on_idle<'b, B>(
+        &self,
+        dma1: &DMA1,
+        buffer: &'b Static<Buffer<B, Dma1Channel5>>,
+    ) -> nb::Result<Ref<'b, [u8]>, dma::Error>
+    where
+        B: Unsize<[u8]>,
+    {
+        let isr = dma1.isr.read();
+
+        let n = if isr.teif5().bit_is_set() {
+            // NOTE the hardware already cleared EN; this mirrors `release`
+            dma1.ifcr.write(|w| w.cteif5().set_bit());
+            dma1.ccr5.modify(|_, w| w.en().clear_bit());
+
+            unsafe { buffer.abort() }
+            self.unlisten(Event::Idle);
+
+            return Err(nb::Error::Other(dma::Error::Transfer));
+        } else if isr.tcif5().bit_is_set() {
+            // the buffer is full
+            buffer.release(dma1)?;
+
+            None
+        } else if self.clear_idle_flag() {
+            dma1.ccr5.modify(|_, w| w.en().clear_bit());
+            // NOTE the transfer may have completed after the ISR was read
+            dma1.ifcr.write(|w| w.ctcif5().set_bit().chtif5().set_bit());
+            let remaining = dma1.cndtr5.read().ndt().bits();
+
+            unsafe { buffer.abort() }
+
+            Some(remaining)
+        } else {
+            return Err(nb::Error::WouldBlock);
+        };
+
+        self.unlisten(Event::Idle);
+
+        Ok(Ref::map(buffer.borrow(), |buffer| {
+            let buffer: &[u8] = buffer;
+            let received = match n {
+                Some(remaining) => buffer.len() - usize(remaining),
+                None => buffer.len(),
+            };
+
+            &buffer[..received]
+        }))
+    }
+}
+
+/// Capacity, in bytes, of each ring buffer of a `BufferedSerial`
+pub const BUFFER_SIZE: usize = 64;
+
+/// Interrupt driven serial interface
+///
+/// Received bytes and bytes pending transmission are stored in two ring
+/// buffers of `BUFFER_SIZE` bytes that are serviced from the USART interrupt
+/// handler. The `Rxne` event must be enabled (see `Serial::listen`) to
+/// receive data; the `Txe` event is managed by this interface.
+///
+/// The buffers are lock-free: `handle_interrupt` is the producer of the RX
+/// buffer and the consumer of the TX buffer. NOTE `try_read` must always be
+/// called from the same execution context, and so must `try_write`
+pub struct BufferedSerial {
+    rx: RingBuffer<u8, [u8; BUFFER_SIZE + 1]>,
+    tx: RingBuffer<u8, [u8; BUFFER_SIZE + 1]>,
+}
+
+impl BufferedSerial {
+    /// Creates an interface with empty buffers
+    pub const fn new() -> Self {
+        BufferedSerial {
+            rx: RingBuffer::new([0; BUFFER_SIZE + 1]),
+            tx: RingBuffer::new([0; BUFFER_SIZE + 1]),
         }
+    }
 
-        let buffer: &[u8] = buffer.lock();
+    /// Takes a received byte out of the RX buffer
+    pub fn try_read(&self) -> nb::Result<u8, !> {
+        self.rx.pop().ok_or(nb::Error::WouldBlock)
+    }
 
-        dma1.cndtr4
-            .write(|w| unsafe { w.ndt().bits(u16(buffer.len()).unwrap()) });
-        dma1.cpar4
-            .write(|w| unsafe { w.bits(&usart1.tdr as *const _ as u32) });
-        dma1.cmar4
-            .write(|w| unsafe { w.bits(buffer.as_ptr() as u32) });
-        dma1.ccr4.modify(|_, w| w.en().set_bit());
+    /// Queues `byte` for transmission
+    ///
+    /// Returns `WouldBlock` if the TX buffer is full
+    pub fn try_write<U>(
+        &self,
+        serial: &Serial<U>,
+        byte: u8,
+    ) -> nb::Result<(), !>
+    where
+        U: Any + Usart,
+    {
+        self.tx.push(byte).map_err(|_| nb::Error::WouldBlock)?;
+        serial.listen(Event::Txe);
 
         Ok(())
     }
+
+    /// Services the USART interrupt
+    ///
+    /// Moves a received byte into the RX buffer and the next queued byte into
+    /// the transmitter. Returns `Error::Overrun` if a byte was received while
+    /// the RX buffer was full; the byte is dropped. Reception errors clear the
+    /// error flags and are reported as well
+    pub fn handle_interrupt<U>(
+        &self,
+        serial: &Serial<U>,
+    ) -> ::core::result::Result<(), Error>
+    where
+        U: Any + Usart,
+    {
+        let usart = serial.0;
+
+        let ret = match hal::serial::Read::read(serial) {
+            Ok(byte) => self.rx.push(byte).map_err(|_| Error::Overrun),
+            Err(nb::Error::Other(e)) => {
+                // NOTE the error flags must be cleared or the interrupt will
+                // fire again
+                usart.icr.write(|w| {
+                    w.orecf()
+                        .set_bit()
+                        .ncf()
+                        .set_bit()
+                        .fecf()
+                        .set_bit()
+                        .pecf()
+                        .set_bit()
+                });
+                Err(e)
+            }
+            Err(nb::Error::WouldBlock) => Ok(()),
+        };
+
+        if usart.isr.read().txe().bit_is_set() {
+            if let Some(byte) = self.tx.pop() {
+                // NOTE(write_volatile) see NOTE in the `read` method
+                unsafe {
+                    ptr::write_volatile(&usart.tdr as *const _ as *mut u8, byte)
+                }
+            }
+
+            if self.tx.is_empty() {
+                serial.unlisten(Event::Txe);
+            }
+        }
+
+        ret
+    }
+}
+
+impl Default for BufferedSerial {
+    fn default() -> Self {
+        BufferedSerial::new()
+    }
 }
@@ -3,41 +3,98 @@
 use core::u16;
 
 use cast::{u16, u32};
+use hal;
+use nb;
 use stm32f30x::{RCC, TIM7};
 
 /// Specialized `Result` type
 pub type Result<T> = ::core::result::Result<T, Error>;
 
 /// An error
+#[derive(Debug)]
 pub struct Error {
     _0: (),
 }
 
+/// Error returned by `with_timeout`
+#[derive(Debug)]
+pub enum TimeoutOr<E> {
+    /// The operation didn't complete before the deadline
+    Timeout,
+    /// The operation failed
+    Other(E),
+}
+
+/// Polls the non-blocking operation `op` until it completes, fails or
+/// `timer` times out
+///
+/// The `timer` is restarted before polling `op` and runs for a single
+/// timeout period, which is the deadline. The `timer` must not be serviced
+/// by an interrupt handler at the same time, as that would clear the timeout.
+pub fn with_timeout<TIM, T, E, F>(
+    timer: &TIM,
+    mut op: F,
+) -> ::core::result::Result<T, TimeoutOr<E>>
+where
+    TIM: hal::Timer,
+    F: FnMut() -> nb::Result<T, E>,
+{
+    timer.restart();
+    // discard a timeout that happened before the restart
+    timer.wait().ok();
+    timer.resume();
+
+    let ret = loop {
+        match op() {
+            Ok(t) => break Ok(t),
+            Err(nb::Error::Other(e)) => break Err(TimeoutOr::Other(e)),
+            Err(nb::Error::WouldBlock) => if timer.wait().is_ok() {
+                break Err(TimeoutOr::Timeout);
+            },
+        }
+    };
+
+    timer.pause();
+
+    ret
+}
+
 /// Periodic timer
 ///
+/// The timer automatically restarts its count after each timeout
+///
 /// # Interrupts
 ///
 /// - `Tim7` - update event
 pub struct Timer<'a>(pub &'a TIM7);
 
 impl<'a> Timer<'a> {
-    /// Initializes the timer with a periodic timeout of `frequency` Hz
+    /// Initializes the timer with a periodic timeout of `timeout`
+    ///
+    /// The prescaler is picked to keep the auto-reload value in its 16-bit
+    /// range. Returns `Err` if the timeout is zero or longer than what the
+    /// timer can count (`2^16 * (2^16 - 1)` ticks)
     ///
     /// NOTE After initialization, the timer will be in the paused state.
-    pub fn init(&self, rcc: &RCC, frequency: u32) {
+    pub fn init<T>(&self, timeout: T, rcc: &RCC) -> Result<()>
+    where
+        T: Into<::apb1::Ticks>,
+    {
+        self._init(timeout.into(), rcc)
+    }
+
+    fn _init(&self, timeout: ::apb1::Ticks, rcc: &RCC) -> Result<()> {
         let tim7 = self.0;
 
         // Power up peripherals
         rcc.apb1enr.modify(|_, w| w.tim7en().enabled());
 
-        let ratio = ::apb1::FREQUENCY / frequency;
-        let psc = u16((ratio - 1) / u32(u16::MAX)).unwrap();
-        tim7.psc.write(|w| w.psc().bits(psc));
-        let arr = u16(ratio / u32(psc + 1)).unwrap();
-        tim7.arr.write(|w| w.arr().bits(arr));
+        self._set_timeout(timeout)?;
 
         tim7.dier.write(|w| w.uie().set_bit());
         tim7.cr1.write(|w| w.opm().continuous());
+
+        Ok(())
     }
 
     /// Clears the update event flag
@@ -54,6 +111,21 @@ impl<'a> Timer<'a> {
         }
     }
 
+    /// Makes the timer output a trigger (TRGO) on every update event
+    ///
+    /// Peripherals that accept the TIM7 TRGO as an external trigger (e.g. the
+    /// DAC) can then be paced by the timer without CPU involvement
+    pub fn enable_trigger_output(&self) {
+        // MMS = 0b010: the update event is selected as TRGO
+        self.0.cr2.modify(|_, w| unsafe { w.mms().bits(0b010) });
+    }
+
+    /// Stops the timer from outputting a trigger on update events
+    pub fn disable_trigger_output(&self) {
+        // MMS = 0b000: the UG bit is selected as TRGO
+        self.0.cr2.modify(|_, w| unsafe { w.mms().bits(0b000) });
+    }
+
     /// Resumes the timer count
     pub fn resume(&self) {
         self.0.cr1.modify(|_, w| w.cen().enabled());
@@ -63,4 +135,61 @@ impl<'a> Timer<'a> {
     pub fn pause(&self) {
         self.0.cr1.modify(|_, w| w.cen().disabled());
     }
+
+    fn _set_timeout(&self, timeout: ::apb1::Ticks) -> Result<()> {
+        let tim7 = self.0;
+
+        let ratio = timeout.0;
+        if ratio == 0 {
+            return Err(Error { _0: () });
+        }
+
+        let psc = u16((ratio - 1) / u32(u16::MAX))
+            .map_err(|_| Error { _0: () })?;
+        tim7.psc.write(|w| w.psc().bits(psc));
+        let arr = u16(ratio / (u32(psc) + 1)).map_err(|_| Error { _0: () })?;
+        tim7.arr.write(|w| w.arr().bits(arr));
+
+        Ok(())
+    }
+}
+
+impl<'a> hal::Timer for Timer<'a> {
+    type Time = ::apb1::Ticks;
+
+    fn get_timeout(&self) -> ::apb1::Ticks {
+        let tim7 = self.0;
+
+        let psc = u32(tim7.psc.read().psc().bits());
+        let arr = u32(tim7.arr.read().arr().bits());
+
+        ::apb1::Ticks((psc + 1) * arr)
+    }
+
+    fn pause(&self) {
+        Timer::pause(self)
+    }
+
+    fn restart(&self) {
+        self.0.cnt.write(|w| w.cnt().bits(0));
+    }
+
+    fn resume(&self) {
+        Timer::resume(self)
+    }
+
+    fn set_timeout<T>(&self, timeout: T)
+    where
+        T: Into<::apb1::Ticks>,
+    {
+        self._set_timeout(timeout.into()).unwrap()
+    }
+
+    fn wait(&self) -> nb::Result<(), !> {
+        if self.clear_update_flag().is_ok() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
 }
@@ -0,0 +1,85 @@
+//! Vector types for multi-axis sensor readings
+//!
+//! Gyroscopes, accelerometers and magnetometers report one signed 16-bit
+//! value per axis. These types give all the sensor readings a common
+//! representation.
+
+use cast::u16;
+
+/// A three axis reading
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct I16x3 {
+    /// X axis
+    pub x: i16,
+    /// Y axis
+    pub y: i16,
+    /// Z axis
+    pub z: i16,
+}
+
+impl I16x3 {
+    /// Parses a reading from its little endian byte representation
+    ///
+    /// The bytes are expected in the order `X_L`, `X_H`, `Y_L`, `Y_H`, `Z_L`,
+    /// `Z_H`, which is how the L3GD20 gyroscope and the LSM303DLHC
+    /// accelerometer lay out their output registers. NOTE the LSM303DLHC
+    /// magnetometer uses a different layout (big endian, X, Z, Y order)
+    pub fn from_le_bytes(bytes: &[u8; 6]) -> Self {
+        I16x3 {
+            x: le(bytes[0], bytes[1]),
+            y: le(bytes[2], bytes[3]),
+            z: le(bytes[4], bytes[5]),
+        }
+    }
+
+    /// Returns the X axis
+    pub fn x(&self) -> i16 {
+        self.x
+    }
+
+    /// Returns the Y axis
+    pub fn y(&self) -> i16 {
+        self.y
+    }
+
+    /// Returns the Z axis
+    pub fn z(&self) -> i16 {
+        self.z
+    }
+}
+
+/// A two axis reading
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct I16x2 {
+    /// X axis
+    pub x: i16,
+    /// Y axis
+    pub y: i16,
+}
+
+impl I16x2 {
+    /// Parses a reading from its little endian byte representation
+    ///
+    /// The bytes are expected in the order `X_L`, `X_H`, `Y_L`, `Y_H`
+    pub fn from_le_bytes(bytes: &[u8; 4]) -> Self {
+        I16x2 {
+            x: le(bytes[0], bytes[1]),
+            y: le(bytes[2], bytes[3]),
+        }
+    }
+
+    /// Returns the X axis
+    pub fn x(&self) -> i16 {
+        self.x
+    }
+
+    /// Returns the Y axis
+    pub fn y(&self) -> i16 {
+        self.y
+    }
+}
+
+/// Combines the low and high bytes of a two's complement value
+fn le(low: u8, high: u8) -> i16 {
+    (u16(high) << 8 | u16(low)) as i16
+}